@@ -0,0 +1,354 @@
+//! Animated image encoders for previews: GIF (LZW via weezl) and APNG (via png).
+//!
+//! Both take full-canvas RGBA frames. GIF needs a palette, so colors are shared across
+//! frames in one global table; when there are more than fit, channel precision is
+//! reduced until they do, which keeps pixel art exact in the common case. The palette
+//! builder is shared with the sixel terminal output.
+
+use std::collections::HashMap;
+
+use anyhow::{Context, Result};
+use image::{Rgba, RgbaImage};
+
+pub struct Frame {
+    pub image: RgbaImage,
+    pub delay_ms: u32,
+}
+
+/// Pixels below this alpha count as transparent in paletted output (GIF, sixel), which
+/// has 1-bit alpha.
+pub const ALPHA_CUTOFF: u8 = 128;
+
+/// Colors shared by a set of images, in first-seen order. When there are more than fit,
+/// channel precision is reduced until they do.
+pub struct Palette {
+    pub colors: Vec<[u8; 3]>,
+    indices: HashMap<[u8; 3], usize>,
+    shift: u32,
+}
+
+impl Palette {
+    /// Builds a palette of at most `max` colors from the visible pixels of `images`.
+    pub fn build<'a>(images: impl Iterator<Item = &'a RgbaImage> + Clone, max: usize) -> Self {
+        let mut shift = 0;
+        let mut indices = HashMap::new();
+        let mut colors = Vec::new();
+        loop {
+            indices.clear();
+            colors.clear();
+            for image in images.clone() {
+                for p in image.pixels() {
+                    if p[3] >= ALPHA_CUTOFF {
+                        let key = quantize([p[0], p[1], p[2]], shift);
+                        indices.entry(key).or_insert_with(|| {
+                            colors.push(key);
+                            colors.len() - 1
+                        });
+                    }
+                }
+            }
+            if colors.len() <= max || shift == 7 {
+                break;
+            }
+            shift += 1;
+        }
+        Palette {
+            colors,
+            indices,
+            shift,
+        }
+    }
+
+    /// The palette index of `p`, or `None` when it is transparent.
+    pub fn index(&self, p: &Rgba<u8>) -> Option<usize> {
+        if p[3] < ALPHA_CUTOFF {
+            return None;
+        }
+        Some(self.indices[&quantize([p[0], p[1], p[2]], self.shift)])
+    }
+}
+
+pub fn encode_gif(frames: &[Frame], looped: bool) -> Result<Vec<u8>> {
+    let Some(first) = frames.first() else {
+        anyhow::bail!("no frames to encode");
+    };
+    let (width, height) = first.image.dimensions();
+    let (Ok(width), Ok(height)) = (u16::try_from(width), u16::try_from(height)) else {
+        anyhow::bail!("{width}x{height} is too large for a GIF (at most 65535x65535)");
+    };
+    let has_transparency = frames
+        .iter()
+        .any(|f| f.image.pixels().any(|p| p[3] < ALPHA_CUTOFF));
+    let reserved = usize::from(has_transparency);
+
+    // Index 0 is the transparent slot when any pixel needs it.
+    let colors = Palette::build(frames.iter().map(|f| &f.image), 256 - reserved);
+    let mut palette = vec![[0u8; 3]; reserved];
+    palette.extend_from_slice(&colors.colors);
+    let table_bits = palette.len().max(2).next_power_of_two().trailing_zeros() as u8;
+    palette.resize(1 << table_bits, [0, 0, 0]);
+
+    let mut out = Vec::new();
+    out.extend_from_slice(b"GIF89a");
+    out.extend_from_slice(&width.to_le_bytes());
+    out.extend_from_slice(&height.to_le_bytes());
+    out.push(0x80 | ((table_bits - 1) << 4) | (table_bits - 1));
+    out.push(0); // background color index
+    out.push(0); // pixel aspect ratio
+    for color in &palette {
+        out.extend_from_slice(color);
+    }
+    if looped {
+        out.extend_from_slice(b"\x21\xFF\x0BNETSCAPE2.0\x03\x01\x00\x00\x00");
+    }
+
+    let min_code_size = table_bits.max(2);
+    for frame in frames {
+        // Restore to background between frames so transparent pixels do not show
+        // the previous frame through.
+        let disposal = if has_transparency { 2 } else { 1 };
+        let delay = frame.delay_ms.div_ceil(10).clamp(2, u16::MAX as u32) as u16;
+        out.extend_from_slice(&[0x21, 0xF9, 0x04, (disposal << 2) | reserved as u8]);
+        out.extend_from_slice(&delay.to_le_bytes());
+        out.extend_from_slice(&[0, 0]);
+
+        out.push(0x2C);
+        out.extend_from_slice(&[0, 0, 0, 0]);
+        out.extend_from_slice(&width.to_le_bytes());
+        out.extend_from_slice(&height.to_le_bytes());
+        out.push(0);
+
+        let indices = frame
+            .image
+            .pixels()
+            .map(|p| colors.index(p).map_or(0, |idx| (idx + reserved) as u8))
+            .collect::<Vec<_>>();
+        let data = weezl::encode::Encoder::new(weezl::BitOrder::Lsb, min_code_size)
+            .encode(&indices)
+            .context("GIF LZW encoding failed")?;
+        out.push(min_code_size);
+        for block in data.chunks(255) {
+            out.push(block.len() as u8);
+            out.extend_from_slice(block);
+        }
+        out.push(0);
+    }
+    out.push(0x3B);
+    Ok(out)
+}
+
+/// Keeps the top `8 - shift` bits of each channel, repeated down into the low bits so
+/// white stays white.
+fn quantize(rgb: [u8; 3], shift: u32) -> [u8; 3] {
+    if shift == 0 {
+        return rgb;
+    }
+    let kept = 8 - shift;
+    rgb.map(|c| {
+        let top = u32::from(c >> shift);
+        let mut value = top;
+        let mut bits = kept;
+        while bits < 8 {
+            value = (value << kept) | top;
+            bits += kept;
+        }
+        (value >> (bits - 8)) as u8
+    })
+}
+
+pub fn encode_apng(frames: &[Frame], looped: bool) -> Result<Vec<u8>> {
+    let Some(first) = frames.first() else {
+        anyhow::bail!("no frames to encode");
+    };
+    let (width, height) = first.image.dimensions();
+    let mut out = Vec::new();
+    let mut encoder = png::Encoder::new(&mut out, width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder
+        .set_animated(frames.len() as u32, if looped { 0 } else { 1 })
+        .context("failed configuring APNG")?;
+    let mut writer = encoder
+        .write_header()
+        .context("failed writing APNG header")?;
+    for frame in frames {
+        let delay = frame.delay_ms.min(u16::MAX as u32) as u16;
+        writer
+            .set_frame_delay(delay, 1000)
+            .context("failed setting APNG frame delay")?;
+        writer
+            .write_image_data(frame.image.as_raw())
+            .context("failed writing APNG frame")?;
+    }
+    writer.finish().context("failed finishing APNG")?;
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    struct GifFrame {
+        delay_cs: u16,
+        pixels: Vec<[u8; 4]>,
+    }
+
+    fn sub_blocks(bytes: &[u8], pos: &mut usize) -> Vec<u8> {
+        let mut data = Vec::new();
+        loop {
+            let len = bytes[*pos] as usize;
+            *pos += 1;
+            if len == 0 {
+                return data;
+            }
+            data.extend_from_slice(&bytes[*pos..*pos + len]);
+            *pos += len;
+        }
+    }
+
+    /// Decodes what `encode_gif` writes: one global table, full-canvas frames.
+    fn decode_gif(bytes: &[u8]) -> (u16, u16, bool, Vec<GifFrame>) {
+        assert_eq!(&bytes[..6], b"GIF89a");
+        let width = u16::from_le_bytes([bytes[6], bytes[7]]);
+        let height = u16::from_le_bytes([bytes[8], bytes[9]]);
+        let table_len = 2usize << (bytes[10] & 7);
+        let palette = bytes[13..13 + table_len * 3].chunks(3).collect::<Vec<_>>();
+        let mut pos = 13 + table_len * 3;
+        let (mut looped, mut delay_cs, mut transparent) = (false, 0, None);
+        let mut frames = Vec::new();
+        loop {
+            match bytes[pos] {
+                0x21 => {
+                    let label = bytes[pos + 1];
+                    pos += 2;
+                    let data = sub_blocks(bytes, &mut pos);
+                    if label == 0xF9 {
+                        delay_cs = u16::from_le_bytes([data[1], data[2]]);
+                        transparent = (data[0] & 1 != 0).then_some(data[3]);
+                    } else if label == 0xFF {
+                        looped |= data.starts_with(b"NETSCAPE2.0");
+                    }
+                }
+                0x2C => {
+                    pos += 10;
+                    let min_code_size = bytes[pos];
+                    pos += 1;
+                    let data = sub_blocks(bytes, &mut pos);
+                    let indices = weezl::decode::Decoder::new(weezl::BitOrder::Lsb, min_code_size)
+                        .decode(&data)
+                        .unwrap();
+                    let pixels = indices
+                        .iter()
+                        .map(|&i| match transparent {
+                            Some(t) if t == i => [0, 0, 0, 0],
+                            _ => {
+                                let c = palette[i as usize];
+                                [c[0], c[1], c[2], 255]
+                            }
+                        })
+                        .collect();
+                    frames.push(GifFrame { delay_cs, pixels });
+                }
+                0x3B => return (width, height, looped, frames),
+                other => panic!("unexpected GIF block {other:#x}"),
+            }
+        }
+    }
+
+    fn frames() -> Vec<Frame> {
+        let a = RgbaImage::from_fn(3, 2, |x, y| match (x + y) % 3 {
+            0 => Rgba([255, 0, 0, 255]),
+            1 => Rgba([0, 255, 0, 255]),
+            _ => Rgba([0, 0, 0, 0]),
+        });
+        let b = RgbaImage::from_fn(3, 2, |x, _| match x {
+            0 => Rgba([255, 255, 255, 255]),
+            1 => Rgba([0, 0, 255, 200]),
+            _ => Rgba([10, 20, 30, 60]),
+        });
+        vec![
+            Frame {
+                image: a,
+                delay_ms: 100,
+            },
+            Frame {
+                image: b,
+                delay_ms: 125,
+            },
+        ]
+    }
+
+    /// The pixels a GIF can represent: 1-bit alpha at `ALPHA_CUTOFF`.
+    fn flattened(image: &RgbaImage) -> Vec<[u8; 4]> {
+        image
+            .pixels()
+            .map(|p| match p[3] >= ALPHA_CUTOFF {
+                true => [p[0], p[1], p[2], 255],
+                false => [0, 0, 0, 0],
+            })
+            .collect()
+    }
+
+    #[test]
+    fn gif_round_trips_frames() {
+        let frames = frames();
+        let (width, height, looped, decoded) = decode_gif(&encode_gif(&frames, true).unwrap());
+        assert_eq!((width, height, looped), (3, 2, true));
+        assert_eq!(decoded.len(), 2);
+        for (frame, gif) in frames.iter().zip(&decoded) {
+            assert_eq!(gif.pixels, flattened(&frame.image));
+        }
+        // Delays round up to centiseconds.
+        assert_eq!(decoded[0].delay_cs, 10);
+        assert_eq!(decoded[1].delay_cs, 13);
+
+        let (_, _, looped, _) = decode_gif(&encode_gif(&frames, false).unwrap());
+        assert!(!looped);
+    }
+
+    #[test]
+    fn gif_rejects_oversized_canvases() {
+        let frame = Frame {
+            image: RgbaImage::new(65536, 1),
+            delay_ms: 100,
+        };
+        assert!(encode_gif(&[frame], true).is_err());
+        assert!(encode_gif(&[], true).is_err());
+    }
+
+    #[test]
+    fn palette_reduces_precision_and_keeps_white() {
+        let image = RgbaImage::from_fn(32, 32, |x, y| {
+            if (x, y) == (0, 0) {
+                Rgba([255, 255, 255, 255])
+            } else {
+                Rgba([(x * 8) as u8, (y * 8) as u8, 0, 255])
+            }
+        });
+        let palette = Palette::build(std::iter::once(&image), 255);
+        assert!(palette.colors.len() <= 255);
+        let white = palette.index(&Rgba([255, 255, 255, 255])).unwrap();
+        assert_eq!(palette.colors[white], [255, 255, 255]);
+        assert_eq!(palette.index(&Rgba([255, 255, 255, 0])), None);
+    }
+
+    #[test]
+    fn apng_round_trips_frames() {
+        let frames = frames();
+        let bytes = encode_apng(&frames, false).unwrap();
+        let mut reader = png::Decoder::new(Cursor::new(bytes)).read_info().unwrap();
+        let control = reader.info().animation_control.unwrap();
+        assert_eq!((control.num_frames, control.num_plays), (2, 1));
+
+        let mut buf = vec![0; reader.output_buffer_size().unwrap()];
+        for frame in &frames {
+            reader.next_frame(&mut buf).unwrap();
+            let fc = reader.info().frame_control.unwrap();
+            assert_eq!(u32::from(fc.delay_num), frame.delay_ms);
+            assert_eq!(fc.delay_den, 1000);
+            assert_eq!(&buf[..], frame.image.as_raw().as_slice());
+        }
+    }
+}
@@ -0,0 +1,784 @@
+//! Minimal reader for Aseprite `.ase`/`.aseprite` files.
+//!
+//! Only what Welder needs to turn a document into flat frames is decoded: layers,
+//! cels (raw, linked and zlib-compressed), palettes and tags. Tilemap layers are skipped
+//! and every layer is composited with the normal blend mode.
+
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use flate2::read::ZlibDecoder;
+use globset::GlobSet;
+use image::{Rgba, RgbaImage};
+
+use crate::blend_pixel;
+
+const HEADER_MAGIC: u16 = 0xA5E0;
+const FRAME_MAGIC: u16 = 0xF1FA;
+
+const CHUNK_OLD_PALETTE: u16 = 0x0004;
+const CHUNK_LAYER: u16 = 0x2004;
+const CHUNK_CEL: u16 = 0x2005;
+const CHUNK_TAGS: u16 = 0x2018;
+const CHUNK_PALETTE: u16 = 0x2019;
+
+const LAYER_VISIBLE: u16 = 1;
+const LAYER_BACKGROUND: u16 = 8;
+const LAYER_REFERENCE: u16 = 64;
+const LAYER_TYPE_GROUP: u16 = 1;
+const LAYER_TYPE_TILEMAP: u16 = 2;
+
+const HEADER_LAYER_OPACITY_VALID: u32 = 1;
+
+/// Layer selection rules from `[inputs.aseprite]`. Patterns match the layer path,
+/// with group names joined by `/` (e.g. `Body/Outline`), or the path of any enclosing
+/// group, so `Shadows` selects every layer inside the `Shadows` group.
+pub struct LayerRules {
+    pub include: Option<GlobSet>,
+    pub exclude: GlobSet,
+}
+
+#[derive(Debug)]
+struct Layer {
+    path: String,
+    flags: u16,
+    kind: u16,
+    opacity: u8,
+    /// Index of the enclosing group layer, if any.
+    parent: Option<usize>,
+}
+
+#[derive(Debug, Clone)]
+enum CelData {
+    Image {
+        x: i32,
+        y: i32,
+        opacity: u8,
+        image: RgbaImage,
+    },
+    Linked(usize),
+}
+
+#[derive(Debug)]
+struct Frame {
+    /// Duration from the frame header, in milliseconds.
+    duration_ms: u16,
+    cels: Vec<(usize, CelData)>,
+}
+
+#[derive(Debug)]
+pub struct Tag {
+    pub name: String,
+    from: usize,
+    to: usize,
+    direction: u8,
+}
+
+#[derive(Debug)]
+pub struct Document {
+    width: u32,
+    height: u32,
+    layers: Vec<Layer>,
+    frames: Vec<Frame>,
+    pub tags: Vec<Tag>,
+}
+
+/// One flattened frame, named the way it will appear in exports.
+pub struct ExpandedFrame {
+    pub path: PathBuf,
+    pub image: RgbaImage,
+    pub duration_ms: u32,
+}
+
+impl Tag {
+    /// Frame indices in playback order for this tag's direction.
+    pub fn frame_order(&self) -> Vec<usize> {
+        let (lo, hi) = (self.from.min(self.to), self.from.max(self.to));
+        let forward = (lo..=hi).collect::<Vec<_>>();
+        let pingpong = || {
+            let mut seq = forward.clone();
+            seq.extend((lo + 1..hi).rev());
+            seq
+        };
+        match self.direction {
+            1 => forward.iter().rev().copied().collect(),
+            2 => pingpong(),
+            3 => {
+                let mut seq = pingpong();
+                seq.reverse();
+                seq
+            }
+            _ => forward,
+        }
+    }
+}
+
+impl Document {
+    pub fn parse(bytes: &[u8]) -> Result<Self> {
+        let mut r = Reader::new(bytes);
+        let _file_size = r.u32()?;
+        if r.u16()? != HEADER_MAGIC {
+            bail!("not an Aseprite file (bad header magic)");
+        }
+        let frame_count = r.u16()? as usize;
+        let width = r.u16()? as u32;
+        let height = r.u16()? as u32;
+        let depth = r.u16()?;
+        let header_flags = r.u32()?;
+        r.skip(2 + 4 + 4)?;
+        let transparent_index = r.u8()?;
+        r.skip(128 - 29)?;
+
+        if !matches!(depth, 8 | 16 | 32) {
+            bail!("unsupported Aseprite color depth {depth}");
+        }
+
+        let mut doc = Document {
+            width,
+            height,
+            layers: Vec::new(),
+            frames: Vec::with_capacity(frame_count),
+            tags: Vec::new(),
+        };
+        let mut palette = vec![Rgba([0, 0, 0, 0]); 256];
+        let mut group_stack: Vec<usize> = Vec::new();
+
+        for frame_idx in 0..frame_count {
+            let frame_len = r.u32()? as usize;
+            let mut fr = Reader::new(r.take(frame_len.saturating_sub(4))?);
+            if fr.u16()? != FRAME_MAGIC {
+                bail!("frame {frame_idx}: bad frame magic");
+            }
+            let old_chunks = fr.u16()? as u32;
+            let duration_ms = fr.u16()?;
+            fr.skip(2)?;
+            let new_chunks = fr.u32()?;
+            let chunk_count = if new_chunks == 0 {
+                old_chunks
+            } else {
+                new_chunks
+            };
+
+            let mut frame = Frame {
+                duration_ms,
+                cels: Vec::new(),
+            };
+
+            for _ in 0..chunk_count {
+                let chunk_len = fr.u32()? as usize;
+                let kind = fr.u16()?;
+                let mut cr = Reader::new(fr.take(chunk_len.saturating_sub(6))?);
+                match kind {
+                    CHUNK_OLD_PALETTE => read_old_palette(&mut cr, &mut palette)?,
+                    CHUNK_PALETTE => read_palette(&mut cr, &mut palette)?,
+                    CHUNK_LAYER => {
+                        let layer =
+                            read_layer(&mut cr, header_flags, &doc.layers, &mut group_stack)?;
+                        doc.layers.push(layer);
+                    }
+                    CHUNK_CEL => {
+                        let ctx = PixelContext {
+                            depth,
+                            palette: &palette,
+                            transparent_index,
+                        };
+                        if let Some(cel) = read_cel(&mut cr, &ctx, &doc.layers)? {
+                            frame.cels.push(cel);
+                        }
+                    }
+                    CHUNK_TAGS => doc.tags = read_tags(&mut cr)?,
+                    _ => {}
+                }
+            }
+            doc.frames.push(frame);
+        }
+
+        Ok(doc)
+    }
+
+    fn layer_visible(&self, idx: usize) -> bool {
+        let mut current = Some(idx);
+        while let Some(i) = current {
+            let layer = &self.layers[i];
+            if layer.flags & LAYER_VISIBLE == 0 || layer.flags & LAYER_REFERENCE != 0 {
+                return false;
+            }
+            current = layer.parent;
+        }
+        true
+    }
+
+    fn layer_selected(&self, idx: usize, rules: &LayerRules) -> bool {
+        let layer = &self.layers[idx];
+        if layer.kind == LAYER_TYPE_GROUP || layer.kind == LAYER_TYPE_TILEMAP {
+            return false;
+        }
+        if !self.layer_visible(idx) {
+            return false;
+        }
+        let matches = |set: &GlobSet| self.layer_paths(idx).any(|path| set.is_match(path));
+        if rules.include.as_ref().is_some_and(|inc| !matches(inc)) {
+            return false;
+        }
+        !matches(&rules.exclude)
+    }
+
+    /// The layer's own path followed by the path of each enclosing group.
+    fn layer_paths(&self, idx: usize) -> impl Iterator<Item = &str> {
+        std::iter::successors(Some(idx), |i| self.layers[*i].parent)
+            .map(|i| self.layers[i].path.as_str())
+    }
+
+    /// Composites every selected layer of `frame` onto a transparent canvas.
+    pub fn render_frame(&self, frame: usize, rules: &LayerRules) -> RgbaImage {
+        let mut canvas = RgbaImage::new(self.width, self.height);
+        let selected = (0..self.layers.len())
+            .filter(|idx| self.layer_selected(*idx, rules))
+            .collect::<Vec<_>>();
+
+        for layer_idx in selected {
+            let Some(cel) = self.find_cel(frame, layer_idx) else {
+                continue;
+            };
+            let CelData::Image {
+                x,
+                y,
+                opacity,
+                image,
+            } = cel
+            else {
+                continue;
+            };
+            let layer_opacity = self.layers[layer_idx].opacity as u32;
+            let opacity = (*opacity as u32 * layer_opacity + 127) / 255;
+            draw_cel(&mut canvas, image, *x, *y, opacity as u8);
+        }
+        canvas
+    }
+
+    fn find_cel(&self, frame: usize, layer: usize) -> Option<&CelData> {
+        let mut frame = frame;
+        // Linked cels may chain; bound the walk by the frame count.
+        for _ in 0..=self.frames.len() {
+            let cel = self
+                .frames
+                .get(frame)?
+                .cels
+                .iter()
+                .find(|(l, _)| *l == layer)?;
+            match &cel.1 {
+                CelData::Linked(target) => frame = *target,
+                data => return Some(data),
+            }
+        }
+        None
+    }
+
+    /// Expands the document into named frames.
+    ///
+    /// - single frame, no tags: `<stem>.png`
+    /// - several frames, no tags: `<stem>_01.png`, `<stem>_02.png`, ...
+    /// - tags: `<stem>/<tag>_01.png`, ... in tag playback order; untagged frames are dropped
+    pub fn expand(&self, source: &Path, rules: &LayerRules) -> Vec<ExpandedFrame> {
+        let stem = source.with_extension("");
+        let frame = |idx: usize, path: PathBuf| ExpandedFrame {
+            path,
+            image: self.render_frame(idx, rules),
+            duration_ms: self.frames[idx].duration_ms as u32,
+        };
+
+        if self.tags.is_empty() {
+            if self.frames.len() == 1 {
+                return vec![frame(0, stem.with_extension("png"))];
+            }
+            let width = frame_number_width(self.frames.len());
+            let name = stem
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            return (0..self.frames.len())
+                .map(|idx| {
+                    let file = format!("{name}_{:0width$}.png", idx + 1);
+                    frame(idx, stem.with_file_name(file))
+                })
+                .collect();
+        }
+
+        let mut out = Vec::new();
+        for tag in &self.tags {
+            let order = tag
+                .frame_order()
+                .into_iter()
+                .filter(|idx| *idx < self.frames.len())
+                .collect::<Vec<_>>();
+            let width = frame_number_width(order.len());
+            for (n, idx) in order.into_iter().enumerate() {
+                let file = format!("{}_{:0width$}.png", tag.name, n + 1);
+                out.push(frame(idx, stem.join(file)));
+            }
+        }
+        out
+    }
+}
+
+fn frame_number_width(count: usize) -> usize {
+    count.to_string().len().max(2)
+}
+
+fn draw_cel(canvas: &mut RgbaImage, cel: &RgbaImage, x: i32, y: i32, opacity: u8) {
+    for (cx, cy, px) in cel.enumerate_pixels() {
+        let tx = x + cx as i32;
+        let ty = y + cy as i32;
+        if tx < 0 || ty < 0 || tx as u32 >= canvas.width() || ty as u32 >= canvas.height() {
+            continue;
+        }
+        let alpha = (px[3] as u32 * opacity as u32 + 127) / 255;
+        if alpha == 0 {
+            continue;
+        }
+        blend_pixel(
+            canvas,
+            tx as u32,
+            ty as u32,
+            Rgba([px[0], px[1], px[2], alpha as u8]),
+        );
+    }
+}
+
+fn read_layer(
+    r: &mut Reader,
+    header_flags: u32,
+    layers: &[Layer],
+    group_stack: &mut Vec<usize>,
+) -> Result<Layer> {
+    let flags = r.u16()?;
+    let kind = r.u16()?;
+    let level = r.u16()? as usize;
+    r.skip(2 + 2 + 2)?;
+    let opacity = r.u8()?;
+    r.skip(3)?;
+    let name = r.string()?;
+
+    group_stack.truncate(level);
+    let parent = group_stack.last().copied();
+    let path = match parent {
+        Some(p) => format!("{}/{name}", layers[p].path),
+        None => name,
+    };
+    if kind == LAYER_TYPE_GROUP {
+        group_stack.push(layers.len());
+    }
+
+    Ok(Layer {
+        path,
+        flags,
+        kind,
+        opacity: if header_flags & HEADER_LAYER_OPACITY_VALID != 0 {
+            opacity
+        } else {
+            255
+        },
+        parent,
+    })
+}
+
+struct PixelContext<'a> {
+    depth: u16,
+    palette: &'a [Rgba<u8>],
+    transparent_index: u8,
+}
+
+fn read_cel(
+    r: &mut Reader,
+    ctx: &PixelContext,
+    layers: &[Layer],
+) -> Result<Option<(usize, CelData)>> {
+    let layer = r.u16()? as usize;
+    let x = r.i16()? as i32;
+    let y = r.i16()? as i32;
+    let opacity = r.u8()?;
+    let kind = r.u16()?;
+    r.skip(2 + 5)?;
+
+    let Some(layer_info) = layers.get(layer) else {
+        bail!("cel references unknown layer {layer}");
+    };
+    let background = layer_info.flags & LAYER_BACKGROUND != 0;
+
+    let data = match kind {
+        0 | 2 => {
+            let w = r.u16()? as u32;
+            let h = r.u16()? as u32;
+            let raw = if kind == 0 {
+                r.rest().to_vec()
+            } else {
+                let mut out = Vec::new();
+                ZlibDecoder::new(r.rest())
+                    .read_to_end(&mut out)
+                    .context("failed inflating compressed cel")?;
+                out
+            };
+            CelData::Image {
+                x,
+                y,
+                opacity,
+                image: decode_pixels(&raw, w, h, ctx, background)?,
+            }
+        }
+        1 => CelData::Linked(r.u16()? as usize),
+        // Compressed tilemaps need the tileset chunk; tilemap layers are not composited.
+        _ => return Ok(None),
+    };
+    Ok(Some((layer, data)))
+}
+
+fn decode_pixels(
+    raw: &[u8],
+    w: u32,
+    h: u32,
+    ctx: &PixelContext,
+    background: bool,
+) -> Result<RgbaImage> {
+    let bpp = (ctx.depth / 8) as usize;
+    let needed = w as usize * h as usize * bpp;
+    if raw.len() < needed {
+        bail!(
+            "cel pixel data is truncated ({} < {needed} bytes)",
+            raw.len()
+        );
+    }
+    let mut img = RgbaImage::new(w, h);
+    for (i, px) in img.pixels_mut().enumerate() {
+        let p = &raw[i * bpp..(i + 1) * bpp];
+        *px = match ctx.depth {
+            32 => Rgba([p[0], p[1], p[2], p[3]]),
+            16 => Rgba([p[0], p[0], p[0], p[1]]),
+            _ => {
+                if p[0] == ctx.transparent_index && !background {
+                    Rgba([0, 0, 0, 0])
+                } else {
+                    ctx.palette[p[0] as usize]
+                }
+            }
+        };
+    }
+    Ok(img)
+}
+
+fn read_palette(r: &mut Reader, palette: &mut Vec<Rgba<u8>>) -> Result<()> {
+    let _size = r.u32()?;
+    let first = r.u32()? as usize;
+    let last = r.u32()? as usize;
+    r.skip(8)?;
+    if first > last {
+        bail!("palette chunk has first index {first} after last index {last}");
+    }
+    if last >= palette.len() {
+        palette.resize(last + 1, Rgba([0, 0, 0, 0]));
+    }
+    for slot in &mut palette[first..=last] {
+        let flags = r.u16()?;
+        *slot = Rgba([r.u8()?, r.u8()?, r.u8()?, r.u8()?]);
+        if flags & 1 != 0 {
+            r.string()?;
+        }
+    }
+    Ok(())
+}
+
+fn read_old_palette(r: &mut Reader, palette: &mut [Rgba<u8>]) -> Result<()> {
+    let packets = r.u16()?;
+    let mut idx = 0usize;
+    for _ in 0..packets {
+        idx += r.u8()? as usize;
+        let count = match r.u8()? {
+            0 => 256,
+            n => n as usize,
+        };
+        for _ in 0..count {
+            let rgb = [r.u8()?, r.u8()?, r.u8()?];
+            if let Some(slot) = palette.get_mut(idx) {
+                *slot = Rgba([rgb[0], rgb[1], rgb[2], 255]);
+            }
+            idx += 1;
+        }
+    }
+    Ok(())
+}
+
+fn read_tags(r: &mut Reader) -> Result<Vec<Tag>> {
+    let count = r.u16()?;
+    r.skip(8)?;
+    let mut tags = Vec::with_capacity(count as usize);
+    for _ in 0..count {
+        let from = r.u16()? as usize;
+        let to = r.u16()? as usize;
+        let direction = r.u8()?;
+        r.skip(2 + 6 + 3 + 1)?;
+        let name = r.string()?;
+        tags.push(Tag {
+            name,
+            from,
+            to,
+            direction,
+        });
+    }
+    Ok(tags)
+}
+
+/// Little-endian cursor with bounds-checked reads.
+struct Reader<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(buf: &'a [u8]) -> Self {
+        Self { buf, pos: 0 }
+    }
+
+    fn take(&mut self, n: usize) -> Result<&'a [u8]> {
+        let end = self
+            .pos
+            .checked_add(n)
+            .filter(|end| *end <= self.buf.len())
+            .context("unexpected end of Aseprite data")?;
+        let out = &self.buf[self.pos..end];
+        self.pos = end;
+        Ok(out)
+    }
+
+    fn rest(&mut self) -> &'a [u8] {
+        let out = &self.buf[self.pos..];
+        self.pos = self.buf.len();
+        out
+    }
+
+    fn skip(&mut self, n: usize) -> Result<()> {
+        self.take(n).map(|_| ())
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        let b = self.take(2)?;
+        Ok(u16::from_le_bytes([b[0], b[1]]))
+    }
+
+    fn i16(&mut self) -> Result<i16> {
+        Ok(self.u16()? as i16)
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        let b = self.take(4)?;
+        Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    fn string(&mut self) -> Result<String> {
+        let len = self.u16()? as usize;
+        Ok(String::from_utf8_lossy(self.take(len)?).into_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use flate2::write::ZlibEncoder;
+    use flate2::Compression;
+    use globset::{Glob, GlobSetBuilder};
+
+    use super::*;
+
+    fn string(s: &str) -> Vec<u8> {
+        let mut out = (s.len() as u16).to_le_bytes().to_vec();
+        out.extend_from_slice(s.as_bytes());
+        out
+    }
+
+    fn chunk(kind: u16, data: &[u8]) -> Vec<u8> {
+        let mut out = (data.len() as u32 + 6).to_le_bytes().to_vec();
+        out.extend_from_slice(&kind.to_le_bytes());
+        out.extend_from_slice(data);
+        out
+    }
+
+    fn layer(name: &str, kind: u16, level: u16) -> Vec<u8> {
+        let mut data = Vec::new();
+        for v in [LAYER_VISIBLE, kind, level, 0, 0, 0] {
+            data.extend_from_slice(&v.to_le_bytes());
+        }
+        data.extend_from_slice(&[255, 0, 0, 0]);
+        data.extend(string(name));
+        chunk(CHUNK_LAYER, &data)
+    }
+
+    fn cel_header(layer: u16, kind: u16) -> Vec<u8> {
+        let mut data = Vec::new();
+        for v in [layer, 0, 0] {
+            data.extend_from_slice(&v.to_le_bytes());
+        }
+        data.push(255);
+        data.extend_from_slice(&kind.to_le_bytes());
+        data.extend_from_slice(&[0; 7]);
+        data
+    }
+
+    /// A `w`x`h` cel filled with `rgba`, zlib-compressed when `compressed`.
+    fn cel(layer: u16, w: u16, h: u16, rgba: [u8; 4], compressed: bool) -> Vec<u8> {
+        let pixels = rgba.repeat(w as usize * h as usize);
+        let mut data = cel_header(layer, if compressed { 2 } else { 0 });
+        data.extend_from_slice(&w.to_le_bytes());
+        data.extend_from_slice(&h.to_le_bytes());
+        if compressed {
+            let mut enc = ZlibEncoder::new(Vec::new(), Compression::default());
+            enc.write_all(&pixels).unwrap();
+            data.extend(enc.finish().unwrap());
+        } else {
+            data.extend(pixels);
+        }
+        chunk(CHUNK_CEL, &data)
+    }
+
+    fn linked_cel(layer: u16, frame: u16) -> Vec<u8> {
+        let mut data = cel_header(layer, 1);
+        data.extend_from_slice(&frame.to_le_bytes());
+        chunk(CHUNK_CEL, &data)
+    }
+
+    fn tags(tags: &[(u16, u16, &str)]) -> Vec<u8> {
+        let mut data = (tags.len() as u16).to_le_bytes().to_vec();
+        data.extend_from_slice(&[0; 8]);
+        for (from, to, name) in tags {
+            data.extend_from_slice(&from.to_le_bytes());
+            data.extend_from_slice(&to.to_le_bytes());
+            data.extend_from_slice(&[0; 1 + 2 + 6 + 3 + 1]);
+            data.extend(string(name));
+        }
+        chunk(CHUNK_TAGS, &data)
+    }
+
+    fn frame(duration_ms: u16, chunks: &[Vec<u8>]) -> Vec<u8> {
+        let body = chunks.concat();
+        let mut out = (body.len() as u32 + 16).to_le_bytes().to_vec();
+        for v in [FRAME_MAGIC, chunks.len() as u16, duration_ms, 0] {
+            out.extend_from_slice(&v.to_le_bytes());
+        }
+        out.extend_from_slice(&(chunks.len() as u32).to_le_bytes());
+        out.extend(body);
+        out
+    }
+
+    fn document(w: u16, h: u16, frames: &[Vec<u8>]) -> Vec<u8> {
+        let body = frames.concat();
+        let mut out = (body.len() as u32 + 128).to_le_bytes().to_vec();
+        for v in [HEADER_MAGIC, frames.len() as u16, w, h, 32] {
+            out.extend_from_slice(&v.to_le_bytes());
+        }
+        out.extend_from_slice(&HEADER_LAYER_OPACITY_VALID.to_le_bytes());
+        out.resize(128, 0);
+        out.extend(body);
+        out
+    }
+
+    fn rules(include: Option<&[&str]>, exclude: &[&str]) -> LayerRules {
+        let set = |patterns: &[&str]| {
+            let mut builder = GlobSetBuilder::new();
+            for p in patterns {
+                builder.add(Glob::new(p).unwrap());
+            }
+            builder.build().unwrap()
+        };
+        LayerRules {
+            include: include.map(set),
+            exclude: set(exclude),
+        }
+    }
+
+    const RED: [u8; 4] = [255, 0, 0, 255];
+    const BLUE: [u8; 4] = [0, 0, 255, 255];
+    const SHADOW: [u8; 4] = [0, 0, 0, 255];
+
+    /// `Body` plus a `Shadows` group holding `Blob`, tagged `walk` over both frames; the
+    /// second frame links the body cel and compresses the shadow.
+    fn walk_document() -> Vec<u8> {
+        document(
+            4,
+            4,
+            &[
+                frame(
+                    80,
+                    &[
+                        layer("Body", 0, 0),
+                        layer("Shadows", LAYER_TYPE_GROUP, 0),
+                        layer("Blob", 0, 1),
+                        cel(0, 2, 2, RED, false),
+                        cel(2, 4, 4, SHADOW, false),
+                        tags(&[(0, 1, "walk")]),
+                    ],
+                ),
+                frame(120, &[linked_cel(0, 0), cel(2, 1, 1, BLUE, true)]),
+            ],
+        )
+    }
+
+    #[test]
+    fn expands_tagged_frames_with_durations() {
+        let doc = Document::parse(&walk_document()).unwrap();
+        let frames = doc.expand(Path::new("hero.aseprite"), &rules(None, &[]));
+        let names = frames.iter().map(|f| f.path.clone()).collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            [
+                PathBuf::from("hero/walk_01.png"),
+                PathBuf::from("hero/walk_02.png")
+            ]
+        );
+        assert_eq!(frames[0].duration_ms, 80);
+        assert_eq!(frames[1].duration_ms, 120);
+        // Blob is drawn after Body, so it covers the top-left corner in frame 1 only.
+        assert_eq!(frames[0].image.get_pixel(0, 0).0, SHADOW);
+        assert_eq!(frames[1].image.get_pixel(0, 0).0, BLUE);
+        assert_eq!(frames[1].image.get_pixel(1, 1).0, RED);
+        assert_eq!(frames[1].image.get_pixel(3, 3).0, [0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn group_paths_select_their_children() {
+        let doc = Document::parse(&walk_document()).unwrap();
+        let excluded = doc.render_frame(0, &rules(None, &["Shadows"]));
+        assert_eq!(excluded.get_pixel(0, 0).0, RED);
+        assert_eq!(excluded.get_pixel(3, 3).0, [0, 0, 0, 0]);
+
+        let included = doc.render_frame(0, &rules(Some(&["Shadows"]), &[]));
+        assert_eq!(included.get_pixel(3, 3).0, SHADOW);
+        assert_eq!(included.get_pixel(0, 0).0, SHADOW);
+    }
+
+    #[test]
+    fn untagged_frames_are_numbered() {
+        let bytes = document(
+            1,
+            1,
+            &[
+                frame(100, &[layer("Body", 0, 0), cel(0, 1, 1, RED, false)]),
+                frame(100, &[cel(0, 1, 1, BLUE, true)]),
+            ],
+        );
+        let doc = Document::parse(&bytes).unwrap();
+        let frames = doc.expand(Path::new("fx/spark.ase"), &rules(None, &[]));
+        assert_eq!(frames[0].path, Path::new("fx/spark_01.png"));
+        assert_eq!(frames[1].path, Path::new("fx/spark_02.png"));
+        assert_eq!(frames[1].image.get_pixel(0, 0).0, BLUE);
+    }
+
+    #[test]
+    fn rejects_truncated_data() {
+        let bytes = walk_document();
+        assert!(Document::parse(&bytes[..bytes.len() - 3]).is_err());
+        assert!(Document::parse(&bytes[..64]).is_err());
+    }
+}
@@ -0,0 +1,296 @@
+//! MaxRects bin packing (best short side fit) for atlas pages.
+//!
+//! Sprites are placed in the order given, so callers control determinism by sorting
+//! first. A sprite goes on the first page it fits on; a new page is opened otherwise.
+
+use anyhow::{bail, Result};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rect {
+    pub x: u32,
+    pub y: u32,
+    pub w: u32,
+    pub h: u32,
+}
+
+impl Rect {
+    fn right(&self) -> u32 {
+        self.x + self.w
+    }
+
+    fn bottom(&self) -> u32 {
+        self.y + self.h
+    }
+
+    fn contains(&self, other: &Rect) -> bool {
+        other.x >= self.x
+            && other.y >= self.y
+            && other.right() <= self.right()
+            && other.bottom() <= self.bottom()
+    }
+
+    fn intersects(&self, other: &Rect) -> bool {
+        self.x < other.right()
+            && other.x < self.right()
+            && self.y < other.bottom()
+            && other.y < self.bottom()
+    }
+}
+
+/// Where one input rectangle ended up.
+#[derive(Debug, Clone, Copy)]
+pub struct Placement {
+    pub page: usize,
+    pub x: u32,
+    pub y: u32,
+}
+
+/// Final page dimensions, cropped to the area actually used.
+#[derive(Debug, Clone, Copy)]
+pub struct PageSize {
+    pub width: u32,
+    pub height: u32,
+}
+
+struct Page {
+    free: Vec<Rect>,
+    used_w: u32,
+    used_h: u32,
+}
+
+impl Page {
+    fn new(width: u32, height: u32) -> Self {
+        Self {
+            free: vec![Rect {
+                x: 0,
+                y: 0,
+                w: width,
+                h: height,
+            }],
+            used_w: 0,
+            used_h: 0,
+        }
+    }
+
+    /// Best short side fit: the free rect leaving the smallest leftover on its
+    /// shorter side, ties broken by the longer side and then position.
+    fn find(&self, w: u32, h: u32) -> Option<Rect> {
+        self.free
+            .iter()
+            .filter(|f| f.w >= w && f.h >= h)
+            .min_by_key(|f| {
+                let dw = f.w - w;
+                let dh = f.h - h;
+                (dw.min(dh), dw.max(dh), f.y, f.x)
+            })
+            .map(|f| Rect {
+                x: f.x,
+                y: f.y,
+                w,
+                h,
+            })
+    }
+
+    fn place(&mut self, used: Rect) {
+        let mut next = Vec::with_capacity(self.free.len() + 4);
+        for free in self.free.drain(..) {
+            if !free.intersects(&used) {
+                next.push(free);
+                continue;
+            }
+            if used.x > free.x {
+                next.push(Rect {
+                    w: used.x - free.x,
+                    ..free
+                });
+            }
+            if used.right() < free.right() {
+                next.push(Rect {
+                    x: used.right(),
+                    w: free.right() - used.right(),
+                    ..free
+                });
+            }
+            if used.y > free.y {
+                next.push(Rect {
+                    h: used.y - free.y,
+                    ..free
+                });
+            }
+            if used.bottom() < free.bottom() {
+                next.push(Rect {
+                    y: used.bottom(),
+                    h: free.bottom() - used.bottom(),
+                    ..free
+                });
+            }
+        }
+
+        // Drop free rects fully contained in another one.
+        let mut pruned: Vec<Rect> = Vec::with_capacity(next.len());
+        for (i, rect) in next.iter().enumerate() {
+            let redundant = next
+                .iter()
+                .enumerate()
+                .any(|(j, other)| i != j && other.contains(rect) && (other != rect || j < i));
+            if !redundant {
+                pruned.push(*rect);
+            }
+        }
+        self.free = pruned;
+        self.used_w = self.used_w.max(used.right());
+        self.used_h = self.used_h.max(used.bottom());
+    }
+}
+
+/// Packs `sizes` onto pages no larger than `max_w` x `max_h`, keeping `padding` pixels
+/// between sprites and around each page's border.
+pub fn pack(
+    sizes: &[(u32, u32)],
+    max_w: u32,
+    max_h: u32,
+    padding: u32,
+) -> Result<(Vec<Placement>, Vec<PageSize>)> {
+    // Each sprite reserves its padding on the right/bottom; the page reserves the
+    // leading padding on the left/top.
+    let inner_w = max_w.saturating_sub(padding);
+    let inner_h = max_h.saturating_sub(padding);
+    let mut pages: Vec<Page> = Vec::new();
+    let mut placements = Vec::with_capacity(sizes.len());
+
+    for (idx, (w, h)) in sizes.iter().enumerate() {
+        let pw = w + padding;
+        let ph = h + padding;
+        if pw > inner_w || ph > inner_h {
+            bail!(
+                "sprite #{idx} ({w}x{h}) does not fit on a {max_w}x{max_h} page with {padding}px padding"
+            );
+        }
+
+        let mut found = pages
+            .iter()
+            .enumerate()
+            .find_map(|(page, p)| p.find(pw, ph).map(|rect| (page, rect)));
+        if found.is_none() {
+            pages.push(Page::new(inner_w, inner_h));
+            let page = pages.len() - 1;
+            found = pages[page].find(pw, ph).map(|rect| (page, rect));
+        }
+        let Some((page, rect)) = found else {
+            bail!("failed packing sprite #{idx}");
+        };
+
+        pages[page].place(rect);
+        placements.push(Placement {
+            page,
+            x: rect.x + padding,
+            y: rect.y + padding,
+        });
+    }
+
+    let sizes = pages
+        .iter()
+        .map(|p| PageSize {
+            width: (p.used_w + padding).max(1),
+            height: (p.used_h + padding).max(1),
+        })
+        .collect();
+    Ok((placements, sizes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Asserts every sprite lies inside its page with `padding` around it and to its
+    /// neighbours.
+    fn assert_layout(sizes: &[(u32, u32)], padding: u32, placed: &[Placement], pages: &[PageSize]) {
+        let rects = placed
+            .iter()
+            .zip(sizes)
+            .map(|(p, &(w, h))| {
+                (
+                    p.page,
+                    Rect {
+                        x: p.x,
+                        y: p.y,
+                        w,
+                        h,
+                    },
+                )
+            })
+            .collect::<Vec<_>>();
+        for (i, (page, rect)) in rects.iter().enumerate() {
+            let size = pages[*page];
+            assert!(rect.x >= padding && rect.y >= padding, "#{i} {rect:?}");
+            assert!(rect.right() + padding <= size.width, "#{i} {rect:?}");
+            assert!(rect.bottom() + padding <= size.height, "#{i} {rect:?}");
+            let grown = Rect {
+                w: rect.w + padding,
+                h: rect.h + padding,
+                ..*rect
+            };
+            for (j, (other_page, other)) in rects.iter().enumerate().skip(i + 1) {
+                let other = Rect {
+                    w: other.w + padding,
+                    h: other.h + padding,
+                    ..*other
+                };
+                assert!(
+                    page != other_page || !grown.intersects(&other),
+                    "#{i} and #{j} overlap"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn packs_one_page_without_overlap() {
+        let sizes = [(16, 8), (8, 8), (8, 16), (4, 4), (12, 6), (8, 8)];
+        let (placed, pages) = pack(&sizes, 64, 64, 0).unwrap();
+        assert_eq!(pages.len(), 1);
+        assert!(placed.iter().all(|p| p.page == 0));
+        assert_eq!((placed[0].x, placed[0].y), (0, 0));
+        assert_layout(&sizes, 0, &placed, &pages);
+    }
+
+    #[test]
+    fn keeps_padding_between_sprites_and_around_pages() {
+        let sizes = [(8, 8); 9];
+        let (placed, pages) = pack(&sizes, 64, 64, 2).unwrap();
+        assert_eq!(pages.len(), 1);
+        assert_eq!((placed[0].x, placed[0].y), (2, 2));
+        assert_layout(&sizes, 2, &placed, &pages);
+        // Rows fill first: 2 + 6 * (8 + 2) = 62 wide, then 2 + 2 * (8 + 2) = 42 tall.
+        assert_eq!((pages[0].width, pages[0].height), (62, 42));
+    }
+
+    #[test]
+    fn overflows_onto_new_pages() {
+        let sizes = [(16, 16); 3];
+        let (placed, pages) = pack(&sizes, 20, 20, 2).unwrap();
+        assert_eq!(pages.len(), 3);
+        for (n, p) in placed.iter().enumerate() {
+            assert_eq!((p.page, p.x, p.y), (n, 2, 2));
+        }
+        assert!(pages.iter().all(|p| (p.width, p.height) == (20, 20)));
+    }
+
+    #[test]
+    fn later_sprites_backfill_earlier_pages() {
+        let sizes = [(16, 16), (16, 16), (4, 4)];
+        let (placed, pages) = pack(&sizes, 20, 24, 0).unwrap();
+        assert_eq!(pages.len(), 2);
+        assert_eq!(placed[2].page, 0);
+        assert_layout(&sizes, 0, &placed, &pages);
+    }
+
+    #[test]
+    fn rejects_sprites_larger_than_a_page() {
+        // 28px + 2px padding on each side is exactly 32px.
+        let (_, pages) = pack(&[(28, 28)], 32, 32, 2).unwrap();
+        assert_eq!((pages[0].width, pages[0].height), (32, 32));
+        assert!(pack(&[(29, 28)], 32, 32, 2).is_err());
+        assert!(pack(&[(8, 33)], 32, 32, 0).is_err());
+    }
+}
@@ -0,0 +1,75 @@
+//! Bevy atlas descriptions: one RON file per page holding a `TextureAtlasLayout`
+//! (`size` plus `textures` rects) and a name-to-index map for looking sprites up.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+
+use super::{create_parent, strip_extension, write_file, ExportInput};
+use crate::atlas_page_name;
+
+#[derive(Serialize)]
+struct BevyAtlas<'a> {
+    image: &'a str,
+    layout: TextureAtlasLayout,
+    names: BTreeMap<&'a str, usize>,
+}
+
+/// Mirrors `bevy::sprite::TextureAtlasLayout` so `layout` deserializes into it directly.
+#[derive(Serialize)]
+struct TextureAtlasLayout {
+    size: (u32, u32),
+    textures: Vec<URect>,
+}
+
+#[derive(Serialize)]
+struct URect {
+    min: (u32, u32),
+    max: (u32, u32),
+}
+
+pub fn export(input: &ExportInput, out: &Path) -> Result<()> {
+    let pretty = ron::ser::PrettyConfig::new()
+        .indentor("    ")
+        .new_line("\n");
+    for packed in input.resolutions {
+        let res_dir = out.join(format!("{}x", packed.factor));
+        for (idx, page) in packed.pages.iter().enumerate() {
+            let image_name = atlas_page_name(idx);
+            let image_path = res_dir.join(&image_name);
+            create_parent(&image_path)?;
+            page.image
+                .save(&image_path)
+                .with_context(|| format!("failed writing {}", image_path.display()))?;
+
+            let atlas = BevyAtlas {
+                image: &image_name,
+                layout: TextureAtlasLayout {
+                    size: (page.image.width(), page.image.height()),
+                    textures: page
+                        .frames
+                        .iter()
+                        .map(|f| URect {
+                            min: (f.x, f.y),
+                            max: (f.x + f.w, f.y + f.h),
+                        })
+                        .collect(),
+                },
+                names: page
+                    .frames
+                    .iter()
+                    .enumerate()
+                    .map(|(i, f)| (strip_extension(&f.name), i))
+                    .collect(),
+            };
+            let mut text = ron::ser::to_string_pretty(&atlas, pretty.clone())
+                .context("failed encoding Bevy atlas")?;
+            text.push('\n');
+            let ron_path = image_path.with_extension("ron");
+            write_file(&ron_path, text)?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,281 @@
+//! GameMaker (2.3+ / 2022+) sprite assets: `sprites/spr_<name>/spr_<name>.yy` plus one
+//! PNG per frame and per frame layer, named by deterministic GUIDs.
+//!
+//! Animations become multi-frame sprites at their configured speed; every other sprite
+//! becomes a single-frame one. Frames are written untrimmed because GameMaker needs all
+//! frames of a sprite at the same size; animations whose frames still differ are skipped
+//! with a warning. The origin comes from the sidecar pivot.
+//! Sprites are parented to the default `Sprites` folder of a new project.
+
+use std::collections::{BTreeMap, HashSet};
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+use image::imageops::{self, FilterType};
+use serde_json::json;
+
+use super::ExportInput;
+use super::{create_parent, source_size, stable_uuid, strip_extension, untrimmed, write_file};
+use crate::normalize_for_glob;
+
+struct GmSprite<'a> {
+    /// Welder name the sprite came from (`hero/walk` or `chars/c01`).
+    source: &'a str,
+    frames: Vec<usize>,
+    fps: f32,
+    looped: bool,
+}
+
+pub fn export(input: &ExportInput, out: &Path) -> Result<()> {
+    let slug = &input.cfg.pack.slug;
+    let names = input
+        .sprites
+        .iter()
+        .map(|s| normalize_for_glob(&s.path))
+        .collect::<Vec<_>>();
+
+    let mut assets: BTreeMap<String, GmSprite> = BTreeMap::new();
+    let mut animated = HashSet::new();
+    for anim in input.animations {
+        animated.extend(anim.frames.iter().copied());
+        insert_asset(
+            &mut assets,
+            GmSprite {
+                source: &anim.name,
+                frames: anim.frames.clone(),
+                fps: anim.fps(),
+                looped: anim.looped,
+            },
+        )?;
+    }
+    for (idx, name) in names.iter().enumerate() {
+        if !animated.contains(&idx) {
+            insert_asset(
+                &mut assets,
+                GmSprite {
+                    source: strip_extension(name),
+                    frames: vec![idx],
+                    fps: 1000.0 / crate::DEFAULT_FRAME_MS as f32,
+                    looped: true,
+                },
+            )?;
+        }
+    }
+
+    // Frames are compared at their untrimmed size, since that is what gets written.
+    assets.retain(|_, asset| {
+        let (w, h) = source_size(&input.sprites[asset.frames[0]]);
+        let Some(&odd) = asset.frames[1..]
+            .iter()
+            .find(|&&idx| source_size(&input.sprites[idx]) != (w, h))
+        else {
+            return true;
+        };
+        let (ow, oh) = source_size(&input.sprites[odd]);
+        eprintln!(
+            "warning: gamemaker: skipping {} (untrimmed frames differ in size: {} is {ow}x{oh}, not {w}x{h})",
+            asset.source, names[odd]
+        );
+        false
+    });
+
+    for packed in input.resolutions {
+        let factor = packed.factor;
+        for (name, asset) in &assets {
+            let first = &input.sprites[asset.frames[0]];
+            let (w, h) = source_size(first);
+            let (w, h) = (w * factor, h * factor);
+
+            let dir = format!("{}x/sprites/{name}", factor);
+            let yy_path = format!("sprites/{name}/{name}.yy");
+            let seed = format!("{slug}:{factor}x:{name}");
+            let layer_id = stable_uuid(&format!("{seed}:layer"));
+
+            // Opaque bounds across all frames, for the collision box.
+            let (mut left, mut top, mut right, mut bottom) = (w, h, 0, 0);
+            let mut frames = Vec::with_capacity(asset.frames.len());
+            let mut keyframes = Vec::with_capacity(asset.frames.len());
+            for (n, &idx) in asset.frames.iter().enumerate() {
+                let sprite = &input.sprites[idx];
+                let (fx, fy, fw, fh) = match sprite.trim {
+                    Some(t) => (t.x, t.y, t.w, t.h),
+                    None => (0, 0, sprite.image.width(), sprite.image.height()),
+                };
+                left = left.min(fx * factor);
+                top = top.min(fy * factor);
+                right = right.max((fx + fw) * factor);
+                bottom = bottom.max((fy + fh) * factor);
+
+                let frame_id = stable_uuid(&format!("{seed}:frame:{n}"));
+                let image = imageops::resize(&untrimmed(sprite), w, h, FilterType::Nearest);
+                let frame_png = out.join(format!("{dir}/{frame_id}.png"));
+                let layer_png = out.join(format!("{dir}/layers/{frame_id}/{layer_id}.png"));
+                for path in [&frame_png, &layer_png] {
+                    create_parent(path)?;
+                    image
+                        .save(path)
+                        .with_context(|| format!("failed writing {}", path.display()))?;
+                }
+
+                frames.push(json!({
+                    "resourceType": "GMSpriteFrame",
+                    "resourceVersion": "1.1",
+                    "name": frame_id,
+                }));
+                keyframes.push(json!({
+                    "resourceType": "Keyframe<SpriteFrameKeyframe>",
+                    "resourceVersion": "1.0",
+                    "id": stable_uuid(&format!("{seed}:key:{n}")),
+                    "Key": n as f32,
+                    "Length": 1.0,
+                    "Stretch": false,
+                    "Disabled": false,
+                    "IsCreationKey": false,
+                    "Channels": {
+                        "0": {
+                            "resourceType": "SpriteFrameKeyframe",
+                            "resourceVersion": "1.0",
+                            "Id": { "name": frame_id, "path": yy_path },
+                        },
+                    },
+                }));
+            }
+            if right <= left || bottom <= top {
+                (left, top, right, bottom) = (0, 0, w, h);
+            }
+
+            let [px, py] = first.meta.pivot();
+            let origin_x = (px * w as f32).round() as i64;
+            let origin_y = (py * h as f32).round() as i64;
+            let key_store = |kind: &str| {
+                json!({
+                    "resourceType": format!("KeyframeStore<{kind}>"),
+                    "resourceVersion": "1.0",
+                    "Keyframes": [],
+                })
+            };
+
+            let track = json!({
+                "resourceType": "GMSpriteFramesTrack",
+                "resourceVersion": "1.0",
+                "name": "frames",
+                "spriteId": null,
+                "keyframes": {
+                    "resourceType": "KeyframeStore<SpriteFrameKeyframe>",
+                    "resourceVersion": "1.0",
+                    "Keyframes": keyframes,
+                },
+                "trackColour": 0,
+                "inheritsTrackColour": true,
+                "builtinName": 0,
+                "traits": 0,
+                "interpolation": 1,
+                "tracks": [],
+                "events": [],
+                "modifiers": [],
+                "isCreationTrack": false,
+            });
+            let sequence = json!({
+                "resourceType": "GMSequence",
+                "resourceVersion": "1.4",
+                "name": name,
+                "timeUnits": 1,
+                "playback": if asset.looped { 1 } else { 0 },
+                "playbackSpeed": asset.fps,
+                "playbackSpeedType": 0,
+                "autoRecord": true,
+                "volume": 1.0,
+                "length": asset.frames.len() as f32,
+                "events": key_store("MessageEventKeyframe"),
+                "moments": key_store("MomentsEventKeyframe"),
+                "tracks": [track],
+                "visibleRange": null,
+                "lockOrigin": false,
+                "showBackdrop": true,
+                "showBackdropImage": false,
+                "backdropImagePath": "",
+                "backdropImageOpacity": 0.5,
+                "backdropWidth": 1366,
+                "backdropHeight": 768,
+                "backdropXOffset": 0.0,
+                "backdropYOffset": 0.0,
+                "xorigin": origin_x,
+                "yorigin": origin_y,
+                "eventToFunction": {},
+                "eventStubScript": null,
+                "parent": { "name": name, "path": yy_path },
+            });
+
+            let yy = json!({
+                "resourceType": "GMSprite",
+                "resourceVersion": "1.0",
+                "name": name,
+                "bboxMode": 0,
+                "collisionKind": 1,
+                "type": 0,
+                "origin": 9,
+                "preMultiplyAlpha": false,
+                "edgeFiltering": false,
+                "collisionTolerance": 0,
+                "swfPrecision": 2.525,
+                "bbox_left": left,
+                "bbox_right": right - 1,
+                "bbox_top": top,
+                "bbox_bottom": bottom - 1,
+                "HTile": false,
+                "VTile": false,
+                "For3D": false,
+                "DynamicTexturePage": false,
+                "width": w,
+                "height": h,
+                "textureGroupId": { "name": "Default", "path": "texturegroups/Default" },
+                "swatchColours": null,
+                "gridX": 0,
+                "gridY": 0,
+                "frames": frames,
+                "sequence": sequence,
+                "layers": [{
+                    "resourceType": "GMImageLayer",
+                    "resourceVersion": "1.0",
+                    "name": layer_id,
+                    "visible": true,
+                    "isLocked": false,
+                    "blendMode": 0,
+                    "opacity": 100.0,
+                    "displayName": "default",
+                }],
+                "nineSlice": null,
+                "parent": { "name": "Sprites", "path": "folders/Sprites.yy" },
+            });
+            let mut text = serde_json::to_string_pretty(&yy).context("failed encoding .yy")?;
+            text.push('\n');
+            write_file(&out.join(format!("{dir}/{name}.yy")), text)?;
+        }
+    }
+    Ok(())
+}
+
+/// `hero/walk` -> `spr_hero_walk`; GameMaker names are `[A-Za-z0-9_]`.
+fn asset_name(source: &str) -> String {
+    let body = source
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect::<String>();
+    format!("spr_{body}")
+}
+
+fn insert_asset<'a>(
+    assets: &mut BTreeMap<String, GmSprite<'a>>,
+    asset: GmSprite<'a>,
+) -> Result<()> {
+    let name = asset_name(asset.source);
+    if let Some(other) = assets.get(&name) {
+        bail!(
+            "{} and {} both map to GameMaker sprite '{name}'",
+            other.source,
+            asset.source
+        );
+    }
+    assets.insert(name, asset);
+    Ok(())
+}
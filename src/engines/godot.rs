@@ -0,0 +1,184 @@
+//! Godot 4 resources: `AtlasTexture` per sprite, `SpriteFrames` per animation group with an
+//! `AnimatedSprite2D` scene using it, and `.import` files for the atlas pages.
+//!
+//! Resources reference each other by relative path, so the folder can be dropped anywhere
+//! under `res://`. Godot 4 no longer stores texture filtering in `.import` files (it is a
+//! CanvasItem / project setting), so the import files only pin lossless, mipmap-free
+//! imports. Nearest filtering is set on the generated scenes, and `override.cfg` makes it the
+//! project default when the folder is used as (or copied into) the project root.
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+
+use super::{create_parent, relative_path, strip_extension, write_file, ExportInput};
+use crate::{atlas_page_name, AtlasFrame};
+
+const IMPORT_FILE: &str = r#"[remap]
+
+importer="texture"
+type="CompressedTexture2D"
+
+[params]
+
+compress/mode=0
+compress/high_quality=false
+compress/lossy_quality=0.7
+compress/hdr_compression=1
+compress/normal_map=0
+compress/channel_pack=0
+mipmaps/generate=false
+mipmaps/limit=-1
+roughness/mode=0
+roughness/src_normal=""
+process/fix_alpha_border=true
+process/premult_alpha=false
+process/normal_map_invert_y=false
+process/hdr_as_srgb=false
+process/hdr_clamp_exposure=false
+process/size_limit=0
+detect_3d/compress_to=0
+"#;
+
+/// Godot reads `override.cfg` from the project root on top of `project.godot`.
+const OVERRIDE_CFG: &str = "[rendering]\n\ntextures/canvas_textures/default_texture_filter=0\n";
+
+pub fn export(input: &ExportInput, out: &Path) -> Result<()> {
+    write_file(&out.join("override.cfg"), OVERRIDE_CFG)?;
+    for packed in input.resolutions {
+        let res_dir = format!("{}x", packed.factor);
+        let mut sprite_paths = BTreeMap::new();
+
+        for (idx, page) in packed.pages.iter().enumerate() {
+            let page_path = format!("{res_dir}/{}", atlas_page_name(idx));
+            let abs = out.join(&page_path);
+            create_parent(&abs)?;
+            page.image
+                .save(&abs)
+                .with_context(|| format!("failed writing {}", abs.display()))?;
+            write_file(&out.join(format!("{page_path}.import")), IMPORT_FILE)?;
+
+            for frame in &page.frames {
+                let tres_path = format!("{res_dir}/sprites/{}.tres", strip_extension(&frame.name));
+                let text = atlas_texture(frame, &relative_path(&tres_path, &page_path));
+                write_file(&out.join(&tres_path), text)?;
+                sprite_paths.insert(frame.name.as_str(), tres_path);
+            }
+        }
+
+        // Animations sharing a folder (`hero/idle`, `hero/walk`) end up in one
+        // SpriteFrames resource; a top-level `torch` animation becomes `torch.tres`.
+        let mut groups: BTreeMap<&str, Vec<(&str, &crate::Animation)>> = BTreeMap::new();
+        for anim in input.animations {
+            let (group, name) = anim
+                .name
+                .rsplit_once('/')
+                .unwrap_or((&anim.name, "default"));
+            groups.entry(group).or_default().push((name, anim));
+        }
+        for (group, anims) in groups {
+            let tres_path = format!("{res_dir}/animations/{group}.tres");
+            let mut textures = Vec::new();
+            let mut entries = Vec::new();
+            for (name, anim) in anims {
+                let mut ids = Vec::with_capacity(anim.frames.len());
+                for &idx in &anim.frames {
+                    let sprite = crate::normalize_for_glob(&input.sprites[idx].path);
+                    let target = sprite_paths
+                        .get(sprite.as_str())
+                        .with_context(|| format!("{sprite} was not packed"))?;
+                    textures.push(relative_path(&tres_path, target));
+                    ids.push(textures.len());
+                }
+                entries.push((name, anim, ids));
+            }
+
+            let mut text = format!(
+                "[gd_resource type=\"SpriteFrames\" load_steps={} format=3]\n\n",
+                textures.len() + 1
+            );
+            for (idx, path) in textures.iter().enumerate() {
+                let _ = writeln!(
+                    text,
+                    "[ext_resource type=\"Texture2D\" path=\"{path}\" id=\"{}\"]",
+                    idx + 1
+                );
+            }
+            text.push_str("\n[resource]\nanimations = [");
+            for (n, (name, anim, ids)) in entries.iter().enumerate() {
+                if n > 0 {
+                    text.push_str(", ");
+                }
+                text.push_str("{\n\"frames\": [");
+                for (i, id) in ids.iter().enumerate() {
+                    if i > 0 {
+                        text.push_str(", ");
+                    }
+                    let _ = write!(
+                        text,
+                        "{{\n\"duration\": 1.0,\n\"texture\": ExtResource(\"{id}\")\n}}"
+                    );
+                }
+                let _ = write!(
+                    text,
+                    "],\n\"loop\": {},\n\"name\": &\"{name}\",\n\"speed\": {:?}\n}}",
+                    anim.looped,
+                    anim.fps()
+                );
+            }
+            text.push_str("]\n");
+            write_file(&out.join(&tres_path), text)?;
+
+            let scene_path = format!("{res_dir}/animations/{group}.tscn");
+            let scene =
+                animated_sprite_scene(group, &relative_path(&scene_path, &tres_path), entries[0].0);
+            write_file(&out.join(&scene_path), scene)?;
+        }
+    }
+    Ok(())
+}
+
+/// An `AnimatedSprite2D` showing `frames`, with nearest filtering (`texture_filter = 1`) so it
+/// stays crisp whatever the project default is.
+fn animated_sprite_scene(group: &str, frames: &str, animation: &str) -> String {
+    let name = group
+        .rsplit('/')
+        .next()
+        .unwrap_or(group)
+        .replace(['.', ':', '@', '%', '"'], "_");
+    format!(
+        "[gd_scene load_steps=2 format=3]\n\n\
+         [ext_resource type=\"SpriteFrames\" path=\"{frames}\" id=\"1\"]\n\n\
+         [node name=\"{name}\" type=\"AnimatedSprite2D\"]\n\
+         texture_filter = 1\n\
+         sprite_frames = ExtResource(\"1\")\n\
+         animation = &\"{animation}\"\n"
+    )
+}
+
+/// An `AtlasTexture` for one packed frame; trimmed frames get a margin restoring the
+/// original size so offsets line up with the untrimmed art.
+fn atlas_texture(frame: &AtlasFrame, page: &str) -> String {
+    let mut text = format!(
+        "[gd_resource type=\"AtlasTexture\" load_steps=2 format=3]\n\n\
+         [ext_resource type=\"Texture2D\" path=\"{page}\" id=\"1\"]\n\n\
+         [resource]\n\
+         atlas = ExtResource(\"1\")\n\
+         region = Rect2({}, {}, {}, {})\n",
+        frame.x, frame.y, frame.w, frame.h
+    );
+    if let Some(trim) = frame.trim {
+        let _ = writeln!(
+            text,
+            "margin = Rect2({}, {}, {}, {})",
+            trim.x,
+            trim.y,
+            trim.source_w - trim.w,
+            trim.source_h - trim.h
+        );
+    }
+    text.push_str("filter_clip = true\n");
+    text
+}
@@ -0,0 +1,83 @@
+//! LDtk tileset definitions, one JSON file per tile sheet and resolution, in the shape of
+//! a project's `defs.tilesets[]` entry. LDtk has no standalone tileset file, so these are
+//! meant to be merged into a project; sidecar properties land in `customData` as JSON.
+//! LDtk grids are square, so non-square tile sizes are skipped with a warning.
+
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use serde_json::json;
+
+use super::tiles::tile_sheets;
+use super::{create_parent, write_file, ExportInput};
+
+pub fn export(input: &ExportInput, out: &Path) -> Result<()> {
+    for packed in input.resolutions {
+        let res_dir = out.join(format!("{}x", packed.factor));
+        for (uid, sheet) in tile_sheets(input, packed.factor)?.into_iter().enumerate() {
+            if sheet.tile_w != sheet.tile_h {
+                eprintln!(
+                    "warning: ldtk: skipping {} (LDtk tilesets need square tiles)",
+                    sheet.name
+                );
+                continue;
+            }
+            let image_name = format!("{}.png", sheet.name);
+            let image_path = res_dir.join(&image_name);
+            create_parent(&image_path)?;
+            sheet
+                .image
+                .save(&image_path)
+                .with_context(|| format!("failed writing {}", image_path.display()))?;
+
+            let mut custom_data = Vec::new();
+            for (id, &idx) in sheet.tiles.iter().enumerate() {
+                let properties = &input.sprites[idx].meta.properties;
+                if properties.is_empty() {
+                    continue;
+                }
+                let data =
+                    serde_json::to_string(properties).context("failed encoding tile properties")?;
+                custom_data.push(json!({ "tileId": id, "data": data }));
+            }
+
+            let rows = (sheet.tiles.len() as u32).div_ceil(sheet.columns);
+            let def = json!({
+                "__cWid": sheet.columns,
+                "__cHei": rows,
+                "identifier": identifier(&sheet.name),
+                "uid": uid + 1,
+                "relPath": image_name,
+                "embedAtlas": null,
+                "pxWid": sheet.image.width(),
+                "pxHei": sheet.image.height(),
+                "tileGridSize": sheet.tile_w,
+                "spacing": sheet.spacing,
+                "padding": sheet.margin,
+                "tags": [],
+                "tagsSourceEnumUid": null,
+                "enumTags": [],
+                "customData": custom_data,
+                "savedSelections": [],
+                "cachedPixelData": null,
+            });
+            let mut text =
+                serde_json::to_string_pretty(&def).context("failed encoding LDtk tileset")?;
+            text.push('\n');
+            write_file(&res_dir.join(format!("{}.json", sheet.name)), text)?;
+        }
+    }
+    Ok(())
+}
+
+/// LDtk identifiers are `[A-Za-z_][A-Za-z0-9_]*`: `tiles-16x16` -> `Tiles_16x16`.
+fn identifier(name: &str) -> String {
+    let mut ident = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect::<String>();
+    if let Some(first) = ident.get(..1) {
+        ident.replace_range(..1, &first.to_ascii_uppercase());
+    }
+    ident
+}
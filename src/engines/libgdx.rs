@@ -0,0 +1,65 @@
+//! LibGDX TexturePacker `.atlas` files, one per resolution covering every page, in the
+//! legacy text format that both old and current `TextureAtlas` loaders read.
+//!
+//! Animation frames (`hero/walk_01.png`) are written as `hero/walk` with an `index`, so
+//! `atlas.findRegions("hero/walk")` returns them in order.
+
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+
+use super::{create_parent, strip_extension, write_file, ExportInput};
+use crate::{atlas_page_name, normalize_for_glob};
+
+pub fn export(input: &ExportInput, out: &Path) -> Result<()> {
+    let mut frame_index = HashMap::new();
+    for anim in input.animations {
+        for (index, &idx) in anim.frames.iter().enumerate() {
+            frame_index.insert(
+                normalize_for_glob(&input.sprites[idx].path),
+                (anim.name.as_str(), index),
+            );
+        }
+    }
+
+    for packed in input.resolutions {
+        let res_dir = out.join(format!("{}x", packed.factor));
+        let mut atlas = String::new();
+        for (idx, page) in packed.pages.iter().enumerate() {
+            let image_name = atlas_page_name(idx);
+            let image_path = res_dir.join(&image_name);
+            create_parent(&image_path)?;
+            page.image
+                .save(&image_path)
+                .with_context(|| format!("failed writing {}", image_path.display()))?;
+
+            let _ = write!(
+                atlas,
+                "\n{image_name}\nsize: {},{}\nformat: RGBA8888\nfilter: Nearest,Nearest\nrepeat: none\n",
+                page.image.width(),
+                page.image.height()
+            );
+            for frame in &page.frames {
+                let (name, index) = match frame_index.get(&frame.name) {
+                    Some((anim, index)) => (*anim, *index as i64),
+                    None => (strip_extension(&frame.name), -1),
+                };
+                // Offsets are measured from the bottom-left of the untrimmed image.
+                let (orig_w, orig_h, offset_x, offset_y) = match frame.trim {
+                    Some(t) => (t.source_w, t.source_h, t.x, t.source_h - t.y - t.h),
+                    None => (frame.w, frame.h, 0, 0),
+                };
+                let _ = write!(
+                    atlas,
+                    "{name}\n  rotate: false\n  xy: {}, {}\n  size: {}, {}\n  orig: {orig_w}, {orig_h}\n  offset: {offset_x}, {offset_y}\n  index: {index}\n",
+                    frame.x, frame.y, frame.w, frame.h
+                );
+            }
+        }
+        let name = format!("{}.atlas", input.cfg.pack.slug);
+        write_file(&res_dir.join(name), atlas)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,161 @@
+//! Engine exporters: turn packed atlases into files a game engine imports directly.
+//!
+//! Every exporter writes into its own folder under `paths.engines` (`dist/engines/<engine>/`),
+//! which `welder package` adds to the zip as `<engine>/`.
+
+use std::fs;
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+use image::{imageops, RgbaImage};
+use sha2::{Digest, Sha256};
+
+use crate::{AtlasPage, Config, Sprite};
+
+mod bevy;
+mod gamemaker;
+mod godot;
+mod ldtk;
+mod libgdx;
+mod rpgmaker;
+mod tiled;
+mod tiles;
+mod unity;
+
+/// Engines accepted by `export.engines` and `welder export --engine`.
+pub const ENGINES: &[&str] = &[
+    "bevy",
+    "gamemaker",
+    "godot",
+    "ldtk",
+    "libgdx",
+    "rpgmaker",
+    "tiled",
+    "unity",
+];
+
+/// Everything an exporter gets: sprites in `sheet.sort` order and their packed pages.
+pub struct ExportInput<'a> {
+    pub cfg: &'a Config,
+    pub sprites: &'a [Sprite],
+    pub resolutions: &'a [PackedResolution],
+    pub animations: &'a [crate::Animation],
+}
+
+/// Atlas pages for one output resolution, as produced by `build_atlas_pages`.
+pub struct PackedResolution {
+    pub factor: u32,
+    pub pages: Vec<AtlasPage>,
+}
+
+pub fn validate_engine(name: &str) -> Result<()> {
+    if !ENGINES.contains(&name) {
+        bail!(
+            "unsupported engine '{name}' (expected one of: {})",
+            ENGINES.join(", ")
+        );
+    }
+    Ok(())
+}
+
+/// Engine-specific config checks for `welder doctor`.
+pub fn validate_config(cfg: &Config, issues: &mut Vec<String>) {
+    if let Some(tiles) = cfg.export.as_ref().and_then(|e| e.tiles.as_ref()) {
+        if let Err(err) = crate::build_globset(&tiles.include) {
+            issues.push(format!("export.tiles.include: {err:#}"));
+        }
+    }
+    for factor in &cfg.build.resolutions {
+        if let Err(err) = unity::pixels_per_unit(cfg, *factor) {
+            issues.push(format!("{err:#}"));
+        }
+    }
+}
+
+/// Writes `engine`'s files into `out`, which the caller has emptied beforehand.
+pub fn export(engine: &str, input: &ExportInput, out: &Path) -> Result<()> {
+    match engine {
+        "bevy" => bevy::export(input, out),
+        "gamemaker" => gamemaker::export(input, out),
+        "godot" => godot::export(input, out),
+        "ldtk" => ldtk::export(input, out),
+        "libgdx" => libgdx::export(input, out),
+        "rpgmaker" => rpgmaker::export(input, out),
+        "tiled" => tiled::export(input, out),
+        "unity" => unity::export(input, out),
+        other => bail!("unsupported engine '{other}'"),
+    }
+}
+
+fn create_parent(path: &Path) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("failed creating {}", parent.display()))?;
+    }
+    Ok(())
+}
+
+/// Writes `contents` to `path`, creating parent directories as needed.
+fn write_file(path: &Path, contents: impl AsRef<[u8]>) -> Result<()> {
+    create_parent(path)?;
+    fs::write(path, contents).with_context(|| format!("failed writing {}", path.display()))
+}
+
+/// Relative path from the directory holding `from` to `to`; both are `/`-separated
+/// and relative to the same root.
+fn relative_path(from: &str, to: &str) -> String {
+    let from_dirs = from.split('/').collect::<Vec<_>>();
+    let from_dirs = &from_dirs[..from_dirs.len() - 1];
+    let to_parts = to.split('/').collect::<Vec<_>>();
+    let common = from_dirs
+        .iter()
+        .zip(&to_parts)
+        .take_while(|(a, b)| a == b)
+        .count();
+    let mut parts = vec![".."; from_dirs.len() - common];
+    parts.extend(&to_parts[common..]);
+    parts.join("/")
+}
+
+/// Deterministic UUID-formatted ID (`8-4-4-4-12` hex) for `seed`.
+fn stable_uuid(seed: &str) -> String {
+    let hex = Sha256::digest(seed.as_bytes())[..16]
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect::<String>();
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
+/// `chars/hero.png` -> `chars/hero`.
+fn strip_extension(name: &str) -> &str {
+    match name.rsplit_once('.') {
+        Some((stem, ext)) if !ext.contains('/') => stem,
+        _ => name,
+    }
+}
+
+/// Size before `build.trim_transparent`.
+fn source_size(sprite: &Sprite) -> (u32, u32) {
+    match sprite.trim {
+        Some(t) => (t.source_w, t.source_h),
+        None => (sprite.image.width(), sprite.image.height()),
+    }
+}
+
+/// The sprite with its trimmed transparent border restored.
+fn untrimmed(sprite: &Sprite) -> RgbaImage {
+    let image = sprite.image.to_rgba8();
+    let Some(t) = sprite.trim else {
+        return image;
+    };
+    let mut canvas = RgbaImage::new(t.source_w, t.source_h);
+    imageops::replace(&mut canvas, &image, t.x as i64, t.y as i64);
+    canvas
+}
@@ -0,0 +1,512 @@
+//! RPG Maker MV/MZ layouts, written to `img/characters/` and `img/tilesets/` so the folder
+//! can be merged into a project as is. Sprites opt in with sidecar tags:
+//!
+//! - `rpgmaker:character`: frames of `<name>/down`, `<name>/left`, `<name>/right` and
+//!   `<name>/up` animations, 3 frames each. Eight characters go on a sheet (4x2 blocks of
+//!   3x4 frames), the layout RPG Maker expects for files without a `$` prefix.
+//! - `rpgmaker:B` .. `rpgmaker:E`: 48px tiles for a 16x16 tileset sheet. The first B cell
+//!   is RPG Maker's "no tile", so B starts at the second cell.
+//! - `rpgmaker:A5`: 48px tiles for the 8x16 A5 sheet.
+//! - `rpgmaker:A1` .. `rpgmaker:A4`: autotile blocks already drawn in RPG Maker's format
+//!   (2x3 tiles, 2x2 for walls), placed at their kind's spot on the sheet. Kinds fill in order,
+//!   or `rpgmaker:A2:5` picks one. Animated A1 kinds take a 3-frame animation or one image
+//!   holding all frames.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+use image::imageops::{self, FilterType};
+use image::{GenericImage, RgbaImage};
+
+use super::{create_parent, source_size, untrimmed, ExportInput};
+use crate::{normalize_for_glob, Sprite};
+
+const TILE_PX: u32 = 48;
+const CHARACTER_TAG: &str = "rpgmaker:character";
+/// Row order of a character block.
+const DIRECTIONS: [&str; 4] = ["down", "left", "right", "up"];
+const FRAMES_PER_DIRECTION: usize = 3;
+const CHARACTERS_PER_SHEET: usize = 8;
+
+/// Tileset sheets: (tag suffix, columns, rows, first usable cell).
+const TILESETS: [(&str, u32, u32, usize); 5] = [
+    ("A5", 8, 16, 0),
+    ("B", 16, 16, 1),
+    ("C", 16, 16, 0),
+    ("D", 16, 16, 0),
+    ("E", 16, 16, 0),
+];
+
+/// Autotile sheets: (tag suffix, columns, rows, kinds).
+const AUTOTILES: [(&str, u32, u32, usize); 4] = [
+    ("A1", 16, 12, 16),
+    ("A2", 16, 12, 32),
+    ("A3", 16, 8, 32),
+    ("A4", 16, 15, 48),
+];
+
+pub fn export(input: &ExportInput, out: &Path) -> Result<()> {
+    let mut tiles: BTreeMap<&str, Vec<usize>> = BTreeMap::new();
+    let mut autotagged: BTreeMap<&str, Vec<(usize, Option<usize>)>> = BTreeMap::new();
+    for (idx, sprite) in input.sprites.iter().enumerate() {
+        for tag in &sprite.meta.tags {
+            let Some(kind) = tag.strip_prefix("rpgmaker:") else {
+                continue;
+            };
+            if kind == "character" {
+                continue;
+            }
+            let (sheet, index) = match kind.split_once(':') {
+                Some((sheet, index)) => (sheet, Some(index)),
+                None => (kind, None),
+            };
+            if let Some((name, .., kinds)) = AUTOTILES.iter().find(|(name, ..)| *name == sheet) {
+                let index = match index {
+                    Some(index) => match index.parse::<usize>() {
+                        Ok(n) if n < *kinds => Some(n),
+                        _ => bail!(
+                            "{}: '{tag}' needs a kind from 0 to {}",
+                            normalize_for_glob(&sprite.path),
+                            kinds - 1
+                        ),
+                    },
+                    None => None,
+                };
+                autotagged.entry(name).or_default().push((idx, index));
+                continue;
+            }
+            match TILESETS.iter().find(|(name, ..)| *name == kind) {
+                Some((name, ..)) => tiles.entry(name).or_default().push(idx),
+                None => bail!(
+                    "{}: unknown tag '{tag}' (expected rpgmaker:character, rpgmaker:A1..A5 or rpgmaker:B..E)",
+                    normalize_for_glob(&sprite.path)
+                ),
+            }
+        }
+    }
+
+    let mut autotiles = BTreeMap::new();
+    for (sheet, tagged) in &autotagged {
+        autotiles.insert(*sheet, autotile_entries(input, sheet, tagged)?);
+    }
+    let characters = characters(input)?;
+    let scale = scale(input, &tiles, &autotiles, &characters)?;
+    let slug = &input.cfg.pack.slug;
+    let mut written = 0;
+
+    if !characters.is_empty() {
+        let (frame_w, frame_h) = source_size(&input.sprites[characters[0].1[0][0]]);
+        for (name, dirs) in &characters {
+            for &idx in dirs.iter().flatten() {
+                if source_size(&input.sprites[idx]) != (frame_w, frame_h) {
+                    bail!(
+                        "character {name}: all frames must be {frame_w}x{frame_h} like {}",
+                        characters[0].0
+                    );
+                }
+            }
+        }
+        let (cell_w, cell_h) = (frame_w * scale, frame_h * scale);
+        let block_w = cell_w * FRAMES_PER_DIRECTION as u32;
+        let block_h = cell_h * DIRECTIONS.len() as u32;
+
+        for (sheet_idx, chunk) in characters.chunks(CHARACTERS_PER_SHEET).enumerate() {
+            let mut sheet = RgbaImage::new(block_w * 4, block_h * 2);
+            for (slot, (_, dirs)) in chunk.iter().enumerate() {
+                let bx = (slot as u32 % 4) * block_w;
+                let by = (slot as u32 / 4) * block_h;
+                for (row, frames) in dirs.iter().enumerate() {
+                    for (col, &idx) in frames.iter().enumerate() {
+                        let x = bx + col as u32 * cell_w;
+                        let y = by + row as u32 * cell_h;
+                        place(&mut sheet, &input.sprites[idx], scale, x, y)?;
+                    }
+                }
+            }
+            save(
+                &sheet,
+                &out.join(format!("img/characters/{slug}-{:02}.png", sheet_idx + 1)),
+            )?;
+            written += 1;
+        }
+    }
+
+    for (name, columns, rows, _) in AUTOTILES {
+        let Some(entries) = autotiles.get(name) else {
+            continue;
+        };
+        let mut sheet = RgbaImage::new(columns * TILE_PX, rows * TILE_PX);
+        for entry in entries {
+            let slot = autotile_slot(name, entry.kind);
+            let (x, y) = (slot.x * TILE_PX, slot.y * TILE_PX);
+            if entry.frames.len() == 1 && slot.frames > 1 {
+                place(&mut sheet, &input.sprites[entry.frames[0]], scale, x, y)?;
+                continue;
+            }
+            for (n, &idx) in entry.frames.iter().enumerate() {
+                let n = n as u32;
+                let (dx, dy) = (slot.step.0 * n * TILE_PX, slot.step.1 * n * TILE_PX);
+                place(&mut sheet, &input.sprites[idx], scale, x + dx, y + dy)?;
+            }
+        }
+        save(&sheet, &out.join(format!("img/tilesets/{slug}_{name}.png")))?;
+        written += 1;
+    }
+
+    for (name, columns, rows, first) in TILESETS {
+        let Some(indices) = tiles.get(name) else {
+            continue;
+        };
+        let capacity = (columns * rows) as usize - first;
+        if indices.len() > capacity {
+            bail!(
+                "rpgmaker:{name} has {} tiles, but the sheet holds {capacity}",
+                indices.len()
+            );
+        }
+        let mut sheet = RgbaImage::new(columns * TILE_PX, rows * TILE_PX);
+        for (n, &idx) in indices.iter().enumerate() {
+            let cell = (n + first) as u32;
+            // B-E sheets are two 8-column halves: cells run down the left half first.
+            let (col, row) = if columns == 16 {
+                let half = cell / (8 * rows);
+                let local = cell % (8 * rows);
+                (half * 8 + local % 8, local / 8)
+            } else {
+                (cell % columns, cell / columns)
+            };
+            place(
+                &mut sheet,
+                &input.sprites[idx],
+                scale,
+                col * TILE_PX,
+                row * TILE_PX,
+            )?;
+        }
+        save(&sheet, &out.join(format!("img/tilesets/{slug}_{name}.png")))?;
+        written += 1;
+    }
+
+    if written == 0 {
+        eprintln!(
+            "warning: rpgmaker: no sprites are tagged rpgmaker:character or rpgmaker:<sheet>"
+        );
+    }
+    Ok(())
+}
+
+/// Upscale factor to RPG Maker's 48px grid: `export.rpgmaker.scale`, or inferred from the
+/// first tileset tile, autotile block or character frame (in that order), or 1.
+fn scale(
+    input: &ExportInput,
+    tiles: &BTreeMap<&str, Vec<usize>>,
+    autotiles: &BTreeMap<&str, Vec<Autotile>>,
+    characters: &[Character],
+) -> Result<u32> {
+    let configured = input
+        .cfg
+        .export
+        .as_ref()
+        .and_then(|e| e.rpgmaker.as_ref())
+        .and_then(|r| r.scale);
+    // A sprite and the width it must reach on the 48px grid.
+    let sample = tiles
+        .values()
+        .flatten()
+        .map(|&idx| (idx, TILE_PX))
+        .chain(autotiles.iter().flat_map(|(sheet, entries)| {
+            entries.iter().map(|entry| {
+                let slot = autotile_slot(sheet, entry.kind);
+                let tiles_w = if entry.frames.len() == slot.frames as usize {
+                    slot.frame.0
+                } else {
+                    slot.area().0
+                };
+                (entry.frames[0], tiles_w * TILE_PX)
+            })
+        }))
+        .chain(characters.first().map(|(_, dirs)| (dirs[0][0], TILE_PX)))
+        .next();
+    let scale = match configured {
+        Some(0) => bail!("export.rpgmaker.scale must be > 0"),
+        Some(scale) => scale,
+        None => match sample {
+            Some((idx, target)) => {
+                let (w, _) = source_size(&input.sprites[idx]);
+                if !target.is_multiple_of(w) {
+                    bail!(
+                        "{} is {w}px wide, which does not scale evenly to {target}px (set export.rpgmaker.scale)",
+                        normalize_for_glob(&input.sprites[idx].path)
+                    );
+                }
+                target / w
+            }
+            None => 1,
+        },
+    };
+
+    for &idx in tiles.values().flatten() {
+        let (w, h) = source_size(&input.sprites[idx]);
+        if (w * scale, h * scale) != (TILE_PX, TILE_PX) {
+            bail!(
+                "{} is {w}x{h}, which is not {TILE_PX}x{TILE_PX} at {scale}x",
+                normalize_for_glob(&input.sprites[idx].path)
+            );
+        }
+    }
+    for (sheet, entries) in autotiles {
+        for entry in entries {
+            check_autotile(input, sheet, entry, scale)?;
+        }
+    }
+    Ok(scale)
+}
+
+/// One autotile kind: a single sprite, or the frames of an animation for animated A1 kinds.
+struct Autotile {
+    kind: usize,
+    frames: Vec<usize>,
+}
+
+/// Where an autotile kind sits on its sheet, in tiles. Animated kinds hold `frames` blocks
+/// of `frame` size, each `step` further along.
+struct Slot {
+    x: u32,
+    y: u32,
+    frame: (u32, u32),
+    frames: u32,
+    step: (u32, u32),
+}
+
+impl Slot {
+    fn area(&self) -> (u32, u32) {
+        (
+            self.frame.0 + self.step.0 * (self.frames - 1),
+            self.frame.1 + self.step.1 * (self.frames - 1),
+        )
+    }
+}
+
+/// Kind positions as RPG Maker MV/MZ's tilemap reads them.
+fn autotile_slot(sheet: &str, kind: usize) -> Slot {
+    let kind = kind as u32;
+    let block = |x, y, frame| Slot {
+        x,
+        y,
+        frame,
+        frames: 1,
+        step: (0, 0),
+    };
+    let water = |x, y| Slot {
+        x,
+        y,
+        frame: (2, 3),
+        frames: 3,
+        step: (2, 0),
+    };
+    let (tx, ty) = (kind % 8, kind / 8);
+    match sheet {
+        "A1" => match kind {
+            0 => water(0, 0),
+            1 => water(0, 3),
+            2 => block(6, 0, (2, 3)),
+            3 => block(6, 3, (2, 3)),
+            _ => {
+                let (x, y) = (tx / 4 * 8, ty * 6 + tx / 2 % 2 * 3);
+                if kind.is_multiple_of(2) {
+                    water(x, y)
+                } else {
+                    // Waterfalls: three 2x1 frames stacked.
+                    Slot {
+                        x: x + 6,
+                        y,
+                        frame: (2, 1),
+                        frames: 3,
+                        step: (0, 1),
+                    }
+                }
+            }
+        },
+        "A2" => block(tx * 2, ty * 3, (2, 3)),
+        "A3" => block(tx * 2, ty * 2, (2, 2)),
+        // Rows alternate between 2x3 wall tops and 2x2 wall sides.
+        _ if ty.is_multiple_of(2) => block(tx * 2, ty / 2 * 5, (2, 3)),
+        _ => block(tx * 2, ty / 2 * 5 + 3, (2, 2)),
+    }
+}
+
+/// Groups `tagged` sprites into autotiles (animations whose frames are all tagged become one
+/// entry) and assigns kinds: explicit ones first, then the lowest free kinds in order.
+fn autotile_entries(
+    input: &ExportInput,
+    sheet: &str,
+    tagged: &[(usize, Option<usize>)],
+) -> Result<Vec<Autotile>> {
+    let kinds = AUTOTILES
+        .iter()
+        .find(|(name, ..)| *name == sheet)
+        .map_or(0, |(.., kinds)| *kinds);
+    let index_of = tagged.iter().copied().collect::<BTreeMap<_, _>>();
+
+    let mut groups = Vec::new();
+    let mut grouped = Vec::new();
+    for anim in input.animations {
+        if anim.frames.len() > 1 && anim.frames.iter().all(|idx| index_of.contains_key(idx)) {
+            groups.push(anim.frames.clone());
+            grouped.extend(anim.frames.iter().copied());
+        }
+    }
+    for &(idx, _) in tagged {
+        if !grouped.contains(&idx) {
+            groups.push(vec![idx]);
+        }
+    }
+    groups.sort_by_key(|frames| frames.iter().min().copied());
+
+    let mut taken = vec![false; kinds];
+    let mut entries = Vec::with_capacity(groups.len());
+    let mut pending = Vec::new();
+    for frames in groups {
+        let name = normalize_for_glob(&input.sprites[frames[0]].path);
+        let explicit = frames.iter().map(|idx| index_of[idx]).collect::<Vec<_>>();
+        if explicit.iter().any(|k| *k != explicit[0]) {
+            bail!("{name}: frames of one autotile must share the same rpgmaker:{sheet} kind");
+        }
+        match explicit[0] {
+            Some(kind) if taken[kind] => {
+                bail!("{name}: rpgmaker:{sheet} kind {kind} is already taken")
+            }
+            Some(kind) => {
+                taken[kind] = true;
+                entries.push(Autotile { kind, frames });
+            }
+            None => pending.push(frames),
+        }
+    }
+    let total = entries.len() + pending.len();
+    let mut free = (0..kinds).filter(|kind| !taken[*kind]);
+    for frames in pending {
+        let Some(kind) = free.next() else {
+            bail!("rpgmaker:{sheet} has {total} autotiles, but the sheet holds {kinds}");
+        };
+        entries.push(Autotile { kind, frames });
+    }
+    entries.sort_by_key(|entry| entry.kind);
+    Ok(entries)
+}
+
+/// Checks an autotile against its kind: one block per frame, or one image of the whole slot.
+fn check_autotile(input: &ExportInput, sheet: &str, entry: &Autotile, scale: u32) -> Result<()> {
+    let slot = autotile_slot(sheet, entry.kind);
+    let (fw, fh) = (slot.frame.0 * TILE_PX, slot.frame.1 * TILE_PX);
+    let (aw, ah) = (slot.area().0 * TILE_PX, slot.area().1 * TILE_PX);
+    let expected = if entry.frames.len() == 1 {
+        (aw, ah)
+    } else {
+        (fw, fh)
+    };
+    let count_ok = entry.frames.len() == 1 || entry.frames.len() == slot.frames as usize;
+    for &idx in &entry.frames {
+        let (w, h) = source_size(&input.sprites[idx]);
+        if !count_ok || (w * scale, h * scale) != expected {
+            let needs = if slot.frames > 1 {
+                format!("{} frames of {fw}x{fh} or one {aw}x{ah} image", slot.frames)
+            } else {
+                format!("one {fw}x{fh} image")
+            };
+            bail!(
+                "{}: rpgmaker:{sheet} kind {} needs {needs}, but has {} frame(s) of {w}x{h} ({}x{} at {scale}x)",
+                normalize_for_glob(&input.sprites[idx].path),
+                entry.kind,
+                entry.frames.len(),
+                w * scale,
+                h * scale
+            );
+        }
+    }
+    Ok(())
+}
+
+type Character<'a> = (&'a str, Vec<Vec<usize>>);
+
+/// Characters by name, each with its frames per direction in `DIRECTIONS` order.
+fn characters<'a>(input: &'a ExportInput) -> Result<Vec<Character<'a>>> {
+    let tagged = |idx: &usize| {
+        input.sprites[*idx]
+            .meta
+            .tags
+            .iter()
+            .any(|t| t == CHARACTER_TAG)
+    };
+
+    let mut found: BTreeMap<&str, BTreeMap<&str, &crate::Animation>> = BTreeMap::new();
+    let mut covered = Vec::new();
+    for anim in input.animations {
+        if !anim.frames.iter().any(tagged) {
+            continue;
+        }
+        let Some((name, dir)) = anim.name.rsplit_once('/') else {
+            bail!(
+                "animation {} is tagged {CHARACTER_TAG} but is not named <character>/<direction>",
+                anim.name
+            );
+        };
+        if !DIRECTIONS.contains(&dir) {
+            bail!(
+                "animation {} is tagged {CHARACTER_TAG}, but '{dir}' is not one of {}",
+                anim.name,
+                DIRECTIONS.join(", ")
+            );
+        }
+        found.entry(name).or_default().insert(dir, anim);
+        covered.extend(anim.frames.iter().copied());
+    }
+    for idx in (0..input.sprites.len()).filter(tagged) {
+        if !covered.contains(&idx) {
+            bail!(
+                "{} is tagged {CHARACTER_TAG} but is not a frame of a <character>/<direction>_NN animation",
+                normalize_for_glob(&input.sprites[idx].path)
+            );
+        }
+    }
+
+    let mut characters = Vec::with_capacity(found.len());
+    for (name, anims) in found {
+        let mut dirs = Vec::with_capacity(DIRECTIONS.len());
+        for dir in DIRECTIONS {
+            let anim = anims
+                .get(dir)
+                .with_context(|| format!("character {name} is missing its '{dir}' animation"))?;
+            if anim.frames.len() != FRAMES_PER_DIRECTION {
+                bail!(
+                    "character {name}: '{dir}' has {} frame(s), RPG Maker needs {FRAMES_PER_DIRECTION}",
+                    anim.frames.len()
+                );
+            }
+            dirs.push(anim.frames.clone());
+        }
+        characters.push((name, dirs));
+    }
+    Ok(characters)
+}
+
+fn place(sheet: &mut RgbaImage, sprite: &Sprite, scale: u32, x: u32, y: u32) -> Result<()> {
+    let image = untrimmed(sprite);
+    let image = imageops::resize(
+        &image,
+        image.width() * scale,
+        image.height() * scale,
+        FilterType::Nearest,
+    );
+    sheet
+        .copy_from(&image, x, y)
+        .with_context(|| format!("failed placing {}", normalize_for_glob(&sprite.path)))
+}
+
+fn save(image: &RgbaImage, path: &Path) -> Result<()> {
+    create_parent(path)?;
+    image
+        .save(path)
+        .with_context(|| format!("failed writing {}", path.display()))
+}
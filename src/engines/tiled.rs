@@ -0,0 +1,85 @@
+//! Tiled tilesets: one `.tsx` per tile sheet and resolution, with sidecar properties as
+//! per-tile custom properties.
+
+use std::fmt::Write as _;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+
+use super::tiles::tile_sheets;
+use super::{create_parent, write_file, ExportInput};
+use crate::PropertyValue;
+
+pub fn export(input: &ExportInput, out: &Path) -> Result<()> {
+    for packed in input.resolutions {
+        let res_dir = out.join(format!("{}x", packed.factor));
+        for sheet in tile_sheets(input, packed.factor)? {
+            let image_name = format!("{}.png", sheet.name);
+            let image_path = res_dir.join(&image_name);
+            create_parent(&image_path)?;
+            sheet
+                .image
+                .save(&image_path)
+                .with_context(|| format!("failed writing {}", image_path.display()))?;
+
+            let mut tsx = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+            let _ = writeln!(
+                tsx,
+                "<tileset version=\"1.10\" tiledversion=\"1.10.2\" name=\"{}\" tilewidth=\"{}\" tileheight=\"{}\" spacing=\"{}\" margin=\"{}\" tilecount=\"{}\" columns=\"{}\">",
+                xml_escape(&sheet.name),
+                sheet.tile_w,
+                sheet.tile_h,
+                sheet.spacing,
+                sheet.margin,
+                sheet.tiles.len(),
+                sheet.columns
+            );
+            let _ = writeln!(
+                tsx,
+                " <image source=\"{}\" width=\"{}\" height=\"{}\"/>",
+                xml_escape(&image_name),
+                sheet.image.width(),
+                sheet.image.height()
+            );
+            for (id, &idx) in sheet.tiles.iter().enumerate() {
+                let properties = &input.sprites[idx].meta.properties;
+                if properties.is_empty() {
+                    continue;
+                }
+                let _ = writeln!(tsx, " <tile id=\"{id}\">\n  <properties>");
+                for (name, value) in properties {
+                    let (kind, value) = match value {
+                        PropertyValue::Bool(v) => (" type=\"bool\"", v.to_string()),
+                        PropertyValue::Int(v) => (" type=\"int\"", v.to_string()),
+                        PropertyValue::Float(v) => (" type=\"float\"", v.to_string()),
+                        PropertyValue::String(v) => ("", xml_escape(v)),
+                    };
+                    let _ = writeln!(
+                        tsx,
+                        "   <property name=\"{}\"{kind} value=\"{value}\"/>",
+                        xml_escape(name)
+                    );
+                }
+                tsx.push_str("  </properties>\n </tile>\n");
+            }
+            tsx.push_str("</tileset>\n");
+            write_file(&res_dir.join(format!("{}.tsx", sheet.name)), tsx)?;
+        }
+    }
+    Ok(())
+}
+
+fn xml_escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for ch in value.chars() {
+        match ch {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\n' => escaped.push_str("&#10;"),
+            _ => escaped.push(ch),
+        }
+    }
+    escaped
+}
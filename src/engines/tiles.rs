@@ -0,0 +1,107 @@
+//! Grid tile sheets shared by the Tiled and LDtk exporters.
+//!
+//! Tiles are grouped by palette variant and untrimmed size, one sheet per group, laid out
+//! left to right in `sheet.sort` order. `sheet.padding_px` becomes both the margin and the spacing, and
+//! the column count is the most that fits in `sheet.max_width`.
+
+use std::collections::BTreeMap;
+
+use anyhow::{bail, Context, Result};
+use image::imageops::{self, FilterType};
+use image::{GenericImage, RgbaImage};
+
+use super::{source_size, untrimmed, ExportInput};
+use crate::{build_globset, normalize_for_glob};
+
+pub struct TileSheet {
+    /// `tiles-16x16`, named after the 1x tile size so names match across resolutions;
+    /// palette variants get `tiles-16x16-<variant>`.
+    pub name: String,
+    pub tile_w: u32,
+    pub tile_h: u32,
+    pub columns: u32,
+    pub margin: u32,
+    pub spacing: u32,
+    pub image: RgbaImage,
+    /// Indices into `ExportInput::sprites`; a tile's ID is its position here.
+    pub tiles: Vec<usize>,
+}
+
+pub fn tile_sheets(input: &ExportInput, factor: u32) -> Result<Vec<TileSheet>> {
+    let cfg = input.cfg;
+    let include = cfg
+        .export
+        .as_ref()
+        .and_then(|e| e.tiles.as_ref())
+        .filter(|t| !t.include.is_empty())
+        .map(|t| build_globset(&t.include))
+        .transpose()
+        .context("invalid export.tiles.include")?;
+    let variants = cfg.palettes.as_ref().map_or(&[][..], |p| &p.targets);
+
+    // Variant sprites are `<variant>/<sprite>`; `include` matches the sprite part.
+    let mut groups: BTreeMap<(Option<&str>, u32, u32), Vec<usize>> = BTreeMap::new();
+    for (idx, sprite) in input.sprites.iter().enumerate() {
+        let path = normalize_for_glob(&sprite.path);
+        let split = path.split_once('/').and_then(|(head, rest)| {
+            let variant = variants.iter().find(|v| *v == head)?;
+            Some((Some(variant.as_str()), rest))
+        });
+        let (variant, name) = split.unwrap_or((None, path.as_str()));
+        if include.as_ref().is_some_and(|inc| !inc.is_match(name)) {
+            continue;
+        }
+        let (w, h) = source_size(sprite);
+        groups.entry((variant, w, h)).or_default().push(idx);
+    }
+
+    let pad = cfg.sheet.padding_px;
+    let mut sheets = Vec::with_capacity(groups.len());
+    for ((variant, w, h), tiles) in groups {
+        let name = match variant {
+            Some(v) => format!("tiles-{w}x{h}-{v}"),
+            None => format!("tiles-{w}x{h}"),
+        };
+        let (tile_w, tile_h) = (w * factor, h * factor);
+        let fit = (cfg.sheet.max_width.saturating_sub(pad * 2) + pad) / (tile_w + pad);
+        if fit == 0 {
+            bail!(
+                "{name} tiles are {tile_w}px wide at {factor}x and do not fit in sheet.max_width ({})",
+                cfg.sheet.max_width
+            );
+        }
+        let columns = fit.min(tiles.len() as u32);
+        let rows = (tiles.len() as u32).div_ceil(columns);
+        let width = pad * 2 + columns * tile_w + (columns - 1) * pad;
+        let height = pad * 2 + rows * tile_h + (rows - 1) * pad;
+        if height > cfg.sheet.max_height {
+            bail!(
+                "{name} needs a {width}x{height} sheet at {factor}x, larger than sheet.max_height ({})",
+                cfg.sheet.max_height
+            );
+        }
+
+        let mut image = RgbaImage::new(width, height);
+        for (n, &idx) in tiles.iter().enumerate() {
+            let n = n as u32;
+            let tile = untrimmed(&input.sprites[idx]);
+            let tile = imageops::resize(&tile, tile_w, tile_h, FilterType::Nearest);
+            let x = pad + (n % columns) * (tile_w + pad);
+            let y = pad + (n / columns) * (tile_h + pad);
+            image
+                .copy_from(&tile, x, y)
+                .context("failed placing tile")?;
+        }
+        sheets.push(TileSheet {
+            name,
+            tile_w,
+            tile_h,
+            columns,
+            margin: pad,
+            spacing: pad,
+            image,
+            tiles,
+        });
+    }
+    Ok(sheets)
+}
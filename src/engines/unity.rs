@@ -0,0 +1,374 @@
+//! Unity `.meta` files: Point-filtered, uncompressed sprite imports with a fixed GUID per
+//! asset, so re-importing a newer pack version keeps scene and prefab references intact.
+//!
+//! Per resolution under `<res>x/`, atlas pages are written in Multiple sprite mode with
+//! one rect per sprite; with `export.unity.loose_sprites` each sprite is also written on
+//! its own in Single mode.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write as _;
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+use image::imageops;
+use sha2::{Digest, Sha256};
+
+use super::{create_parent, write_file, ExportInput};
+use crate::{atlas_page_name, AtlasFrame, Config, UnityPixelsPerUnit};
+
+const DEFAULT_PIXELS_PER_UNIT: f32 = 16.0;
+
+/// Pixels per unit at `factor`x: a single number applies to 1x and scales with the
+/// resolution so sprites keep their world size; a table sets each resolution explicitly.
+pub fn pixels_per_unit(cfg: &Config, factor: u32) -> Result<f32> {
+    let setting = cfg
+        .export
+        .as_ref()
+        .and_then(|e| e.unity.as_ref())
+        .and_then(|u| u.pixels_per_unit.as_ref());
+    let ppu = match setting {
+        None => DEFAULT_PIXELS_PER_UNIT * factor as f32,
+        Some(UnityPixelsPerUnit::Base(base)) => base * factor as f32,
+        Some(UnityPixelsPerUnit::PerResolution(table)) => *table
+            .get(&format!("{factor}x"))
+            .with_context(|| format!("export.unity.pixels_per_unit has no entry for {factor}x"))?,
+    };
+    if !(ppu.is_finite() && ppu > 0.0) {
+        bail!("export.unity.pixels_per_unit must be > 0 (got {ppu} at {factor}x)");
+    }
+    Ok(ppu)
+}
+
+pub fn export(input: &ExportInput, out: &Path) -> Result<()> {
+    let slug = &input.cfg.pack.slug;
+    let loose = input
+        .cfg
+        .export
+        .as_ref()
+        .and_then(|e| e.unity.as_ref())
+        .is_some_and(|u| u.loose_sprites);
+    let mut folders = BTreeSet::new();
+
+    for packed in input.resolutions {
+        let res_dir = format!("{}x", packed.factor);
+        let ppu = pixels_per_unit(input.cfg, packed.factor)?;
+        let mut names = BTreeMap::new();
+
+        for (idx, page) in packed.pages.iter().enumerate() {
+            let page_path = format!("{res_dir}/{}", atlas_page_name(idx));
+            let abs = out.join(&page_path);
+            create_parent(&abs)?;
+            page.image
+                .save(&abs)
+                .with_context(|| format!("failed writing {}", abs.display()))?;
+
+            let mut rects = Vec::with_capacity(page.frames.len());
+            for frame in &page.frames {
+                let name = sprite_name(&frame.name);
+                if let Some(other) = names.insert(name.clone(), frame.name.as_str()) {
+                    bail!(
+                        "{other} and {} both map to Unity sprite name '{name}'",
+                        frame.name
+                    );
+                }
+                rects.push(SpriteRect {
+                    name,
+                    x: frame.x,
+                    y: page.image.height() - frame.y - frame.h,
+                    w: frame.w,
+                    h: frame.h,
+                    pivot: unity_pivot(frame),
+                    id_seed: format!("{slug}:{page_path}:{}", frame.name),
+                });
+                if !loose {
+                    continue;
+                }
+
+                let sprite_path = format!("{res_dir}/sprites/{}", frame.name);
+                let abs = out.join(&sprite_path);
+                create_parent(&abs)?;
+                imageops::crop_imm(&page.image, frame.x, frame.y, frame.w, frame.h)
+                    .to_image()
+                    .save(&abs)
+                    .with_context(|| format!("failed writing {}", abs.display()))?;
+                let meta = texture_meta(
+                    &guid(slug, &sprite_path),
+                    ppu,
+                    frame.w.max(frame.h),
+                    Mode::Single(unity_pivot(frame)),
+                );
+                write_file(&out.join(format!("{sprite_path}.meta")), meta)?;
+                record_folders(&mut folders, &sprite_path);
+            }
+
+            let size = page.image.width().max(page.image.height());
+            let meta = texture_meta(&guid(slug, &page_path), ppu, size, Mode::Multiple(&rects));
+            write_file(&out.join(format!("{page_path}.meta")), meta)?;
+            record_folders(&mut folders, &page_path);
+        }
+    }
+
+    // Unity generates folder metas with random GUIDs unless they ship with the assets.
+    for folder in folders {
+        let meta = format!(
+            "fileFormatVersion: 2\nguid: {}\nfolderAsset: yes\nDefaultImporter:\n  externalObjects: {{}}\n  userData:\n  assetBundleName:\n  assetBundleVariant:\n",
+            guid(slug, &folder)
+        );
+        write_file(&out.join(format!("{folder}.meta")), meta)?;
+    }
+    Ok(())
+}
+
+/// `hero/walk_01.png` -> `hero_walk_01`; Unity sprite names cannot contain `/`.
+fn sprite_name(name: &str) -> String {
+    super::strip_extension(name).replace('/', "_")
+}
+
+/// Double-quoted YAML scalar, so names like `yes`, `1_2` or `a: b` stay strings.
+fn yaml_quote(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for ch in value.chars() {
+        match ch {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            c if c.is_control() => {
+                let _ = write!(quoted, "\\u{:04x}", c as u32);
+            }
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+fn record_folders(folders: &mut BTreeSet<String>, file: &str) {
+    let mut dir = file;
+    while let Some((parent, _)) = dir.rsplit_once('/') {
+        folders.insert(parent.to_string());
+        dir = parent;
+    }
+}
+
+/// Deterministic 32-hex-digit GUID for an asset path within the pack.
+fn guid(slug: &str, path: &str) -> String {
+    Sha256::digest(format!("{slug}:{path}").as_bytes())[..16]
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+/// Stable 64-bit local file ID for a sprite inside a Multiple-mode texture.
+fn internal_id(seed: &str) -> i64 {
+    let digest = Sha256::digest(seed.as_bytes());
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&digest[..8]);
+    // Keep it positive; Unity reserves some small and negative IDs.
+    (i64::from_le_bytes(bytes) & i64::MAX).max(1 << 32)
+}
+
+/// Welder pivots are top-down and relative to the untrimmed sprite; Unity's are
+/// bottom-up and relative to the (trimmed) rect.
+fn unity_pivot(frame: &AtlasFrame) -> (f32, f32) {
+    let [px, py] = frame.pivot;
+    match frame.trim {
+        Some(t) => {
+            let x = (px * t.source_w as f32 - t.x as f32) / t.w as f32;
+            let y = (py * t.source_h as f32 - t.y as f32) / t.h as f32;
+            (x, 1.0 - y)
+        }
+        None => (px, 1.0 - py),
+    }
+}
+
+struct SpriteRect {
+    name: String,
+    x: u32,
+    y: u32,
+    w: u32,
+    h: u32,
+    pivot: (f32, f32),
+    id_seed: String,
+}
+
+enum Mode<'a> {
+    Single((f32, f32)),
+    Multiple(&'a [SpriteRect]),
+}
+
+fn texture_meta(asset_guid: &str, ppu: f32, size: u32, mode: Mode) -> String {
+    let (sprite_mode, pivot) = match mode {
+        Mode::Single(pivot) => (1, pivot),
+        Mode::Multiple(_) => (2, (0.5, 0.5)),
+    };
+    // Anything smaller than the image makes Unity downscale it on import.
+    let max_size = size.next_power_of_two().clamp(32, 16384);
+
+    let mut text = String::new();
+    let _ = write!(
+        text,
+        "fileFormatVersion: 2
+guid: {asset_guid}
+TextureImporter:
+  internalIDToNameTable: []
+  externalObjects: {{}}
+  serializedVersion: 12
+  mipmaps:
+    mipMapMode: 0
+    enableMipMap: 0
+    sRGBTexture: 1
+    linearTexture: 0
+    fadeOut: 0
+    borderMipMap: 0
+    mipMapsPreserveCoverage: 0
+    alphaTestReferenceValue: 0.5
+    mipMapFadeDistanceStart: 1
+    mipMapFadeDistanceEnd: 3
+  bumpmap:
+    convertToNormalMap: 0
+    externalNormalMap: 0
+    heightScale: 0.25
+    normalMapFilter: 0
+  isReadable: 0
+  streamingMipmaps: 0
+  streamingMipmapsPriority: 0
+  vTOnly: 0
+  ignoreMasterTextureLimit: 0
+  grayScaleToAlpha: 0
+  generateCubemap: 6
+  cubemapConvolution: 0
+  seamlessCubemap: 0
+  textureFormat: 1
+  maxTextureSize: {max_size}
+  textureSettings:
+    serializedVersion: 2
+    filterMode: 0
+    aniso: 1
+    mipBias: 0
+    wrapU: 1
+    wrapV: 1
+    wrapW: 1
+  nPOTScale: 0
+  lightmap: 0
+  compressionQuality: 50
+  spriteMode: {sprite_mode}
+  spriteExtrude: 1
+  spriteMeshType: 0
+  alignment: 9
+  spritePivot: {{x: {}, y: {}}}
+  spritePixelsToUnits: {ppu}
+  spriteBorder: {{x: 0, y: 0, z: 0, w: 0}}
+  spriteGenerateFallbackPhysicsShape: 1
+  alphaUsage: 1
+  alphaIsTransparency: 1
+  spriteTessellationDetail: -1
+  textureType: 8
+  textureShape: 1
+  singleChannelComponent: 0
+  flipbookRows: 1
+  flipbookColumns: 1
+  maxTextureSizeSet: 0
+  compressionQualitySet: 0
+  textureFormatSet: 0
+  ignorePngGamma: 0
+  applyGammaDecoding: 0
+  platformSettings:
+  - serializedVersion: 3
+    buildTarget: DefaultTexturePlatform
+    maxTextureSize: {max_size}
+    resizeAlgorithm: 0
+    textureFormat: -1
+    textureCompression: 0
+    compressionQuality: 50
+    crunchedCompression: 0
+    allowsAlphaSplitting: 0
+    overridden: 0
+    androidETC2FallbackOverride: 0
+    forceMaximumCompressionQuality_BC6H_BC7: 0
+  spriteSheet:
+    serializedVersion: 2
+",
+        pivot.0, pivot.1
+    );
+
+    match mode {
+        Mode::Multiple(rects) if !rects.is_empty() => {
+            text.push_str("    sprites:\n");
+            for rect in rects {
+                let id = internal_id(&rect.id_seed);
+                let _ = write!(
+                    text,
+                    "    - serializedVersion: 2
+      name: {}
+      rect:
+        serializedVersion: 2
+        x: {}
+        y: {}
+        width: {}
+        height: {}
+      alignment: 9
+      pivot: {{x: {}, y: {}}}
+      border: {{x: 0, y: 0, z: 0, w: 0}}
+      outline: []
+      physicsShape: []
+      tessellationDetail: 0
+      bones: []
+      spriteID: {}
+      internalID: {id}
+      vertices: []
+      indices:
+      edges: []
+      weights: []
+",
+                    yaml_quote(&rect.name),
+                    rect.x,
+                    rect.y,
+                    rect.w,
+                    rect.h,
+                    rect.pivot.0,
+                    rect.pivot.1,
+                    &guid("sprite", &rect.id_seed)
+                );
+            }
+        }
+        _ => text.push_str("    sprites: []\n"),
+    }
+
+    text.push_str(
+        "    outline: []
+    physicsShape: []
+    bones: []
+    spriteID:
+    internalID: 0
+    vertices: []
+    indices:
+    edges: []
+    weights: []
+    secondaryTextures: []
+",
+    );
+    match mode {
+        Mode::Multiple(rects) if !rects.is_empty() => {
+            text.push_str("    nameFileIdTable:\n");
+            let mut table = rects
+                .iter()
+                .map(|r| (r.name.as_str(), internal_id(&r.id_seed)))
+                .collect::<Vec<_>>();
+            table.sort();
+            for (name, id) in table {
+                let _ = writeln!(text, "      {}: {id}", yaml_quote(name));
+            }
+        }
+        _ => text.push_str("    nameFileIdTable: {}\n"),
+    }
+    text.push_str(
+        "  spritePackingTag:
+  pSDRemoveMatte: 0
+  pSDShowRemoveMatteOption: 0
+  userData:
+  assetBundleName:
+  assetBundleVariant:
+",
+    );
+    text
+}
@@ -0,0 +1,624 @@
+//! Bitmap fonts for preview text: BDF, PCF, or a PNG strip of equally wide glyphs with a
+//! glyph map. Characters a font lacks fall back to the bundled misc-fixed 5x8 font, which
+//! covers Latin-1.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+use image::{Rgba, RgbaImage};
+
+use crate::blend_pixel;
+
+const FALLBACK_BDF: &str = include_str!("../fonts/misc-fixed-5x8.bdf");
+
+/// Pixels at or above this alpha are ink in PNG strip fonts.
+const STRIP_ALPHA_CUTOFF: u8 = 128;
+
+const PCF_MAGIC: &[u8; 4] = b"\x01fcp";
+const PCF_ACCELERATORS: u32 = 1 << 1;
+const PCF_METRICS: u32 = 1 << 2;
+const PCF_BITMAPS: u32 = 1 << 3;
+const PCF_BDF_ENCODINGS: u32 = 1 << 5;
+const PCF_BDF_ACCELERATORS: u32 = 1 << 8;
+const PCF_COMPRESSED_METRICS: u32 = 0x100;
+const PCF_BYTE_MSB: u32 = 1 << 2;
+const PCF_BIT_MSB: u32 = 1 << 3;
+const PCF_NO_GLYPH: u16 = 0xFFFF;
+
+#[derive(Debug, Clone)]
+struct Glyph {
+    width: u32,
+    height: u32,
+    /// Left edge of the bitmap relative to the pen position.
+    x_off: i32,
+    /// Bottom edge of the bitmap above the baseline.
+    y_off: i32,
+    advance: i32,
+    /// Row-major, `width * height` entries.
+    bits: Vec<bool>,
+}
+
+#[derive(Debug)]
+pub struct Font {
+    ascent: i32,
+    descent: i32,
+    glyphs: HashMap<char, Glyph>,
+    default_char: Option<char>,
+}
+
+impl Font {
+    /// The bundled misc-fixed 5x8 font (ASCII and Latin-1).
+    pub fn fallback() -> Font {
+        parse_bdf(FALLBACK_BDF).expect("bundled fallback font is valid BDF")
+    }
+
+    /// Loads a `.bdf`, `.pcf` or `.png` font. PNG strips need `glyphs`, the characters
+    /// of the strip from left to right.
+    pub fn load(path: &Path, glyphs: Option<&str>) -> Result<Font> {
+        let ext = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(str::to_ascii_lowercase);
+        let font = match ext.as_deref() {
+            Some("bdf") => fs::read_to_string(path)
+                .context("failed reading font")
+                .and_then(|text| parse_bdf(&text)),
+            Some("pcf") => fs::read(path)
+                .context("failed reading font")
+                .and_then(|data| parse_pcf(&data)),
+            Some("png") => {
+                let glyphs = glyphs.context("PNG strip fonts need a glyphs map")?;
+                image::open(path)
+                    .context("failed reading font")
+                    .and_then(|image| parse_strip(&image.to_rgba8(), glyphs))
+            }
+            _ => bail!(
+                "unsupported font {} (expected .bdf, .pcf or .png)",
+                path.display()
+            ),
+        };
+        let mut font = font.with_context(|| format!("failed loading font {}", path.display()))?;
+
+        for (ch, glyph) in Font::fallback().glyphs {
+            font.glyphs.entry(ch).or_insert(glyph);
+        }
+        Ok(font)
+    }
+
+    fn glyph(&self, ch: char) -> Option<&Glyph> {
+        self.glyphs
+            .get(&ch)
+            .or_else(|| self.glyphs.get(&self.default_char?))
+    }
+
+    /// Size of `text` in pixels at `scale`: the inked width and the line height.
+    pub fn measure(&self, text: &str, scale: u32) -> (u32, u32) {
+        let mut pen = 0i32;
+        let mut right = 0i32;
+        for glyph in text.chars().filter_map(|ch| self.glyph(ch)) {
+            right = right.max(pen + glyph.x_off + glyph.width as i32);
+            pen += glyph.advance;
+        }
+        let height = (self.ascent + self.descent).max(0) as u32;
+        (right.max(0) as u32 * scale, height * scale)
+    }
+
+    /// Blends `text` onto `image` with the top of the line at `y`.
+    pub fn draw(
+        &self,
+        image: &mut RgbaImage,
+        text: &str,
+        x: i64,
+        y: i64,
+        scale: u32,
+        color: Rgba<u8>,
+    ) {
+        let scale = i64::from(scale.max(1));
+        let mut pen = 0i64;
+        for glyph in text.chars().filter_map(|ch| self.glyph(ch)) {
+            let left = x + (pen + i64::from(glyph.x_off)) * scale;
+            let top = y + i64::from(self.ascent - glyph.y_off - glyph.height as i32) * scale;
+            for row in 0..glyph.height {
+                for col in 0..glyph.width {
+                    if !glyph.bits[(row * glyph.width + col) as usize] {
+                        continue;
+                    }
+                    for dy in 0..scale {
+                        for dx in 0..scale {
+                            let px = left + i64::from(col) * scale + dx;
+                            let py = top + i64::from(row) * scale + dy;
+                            if px < 0
+                                || py < 0
+                                || px >= i64::from(image.width())
+                                || py >= i64::from(image.height())
+                            {
+                                continue;
+                            }
+                            blend_pixel(image, px as u32, py as u32, color);
+                        }
+                    }
+                }
+            }
+            pen += i64::from(glyph.advance);
+        }
+    }
+}
+
+fn parse_bdf(text: &str) -> Result<Font> {
+    let mut lines = text.lines().map(str::trim);
+    if !lines.next().is_some_and(|l| l.starts_with("STARTFONT")) {
+        bail!("not a BDF font (missing STARTFONT)");
+    }
+
+    let mut bbox = None;
+    let mut ascent = None;
+    let mut descent = None;
+    let mut default_char = None;
+    let mut glyphs = HashMap::new();
+    while let Some(line) = lines.next() {
+        let mut words = line.split_whitespace();
+        match words.next() {
+            Some("FONTBOUNDINGBOX") => bbox = Some(bdf_ints::<4>(words, line)?),
+            Some("FONT_ASCENT") => ascent = Some(bdf_ints::<1>(words, line)?[0]),
+            Some("FONT_DESCENT") => descent = Some(bdf_ints::<1>(words, line)?[0]),
+            Some("DEFAULT_CHAR") => {
+                default_char = char::from_u32(bdf_ints::<1>(words, line)?[0] as u32)
+            }
+            Some("STARTCHAR") => {
+                if let Some((ch, glyph)) = parse_bdf_char(&mut lines)? {
+                    glyphs.insert(ch, glyph);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let [_, bbox_h, _, bbox_y] = bbox.context("BDF font has no FONTBOUNDINGBOX")?;
+    Ok(Font {
+        ascent: ascent.unwrap_or(bbox_h + bbox_y),
+        descent: descent.unwrap_or(-bbox_y),
+        glyphs,
+        default_char,
+    })
+}
+
+/// Reads one `STARTCHAR` .. `ENDCHAR` block; unencoded glyphs are skipped.
+fn parse_bdf_char<'a>(lines: &mut impl Iterator<Item = &'a str>) -> Result<Option<(char, Glyph)>> {
+    let mut encoding = None;
+    let mut advance = None;
+    let mut bbx = None;
+    loop {
+        let line = lines.next().context("BDF glyph is missing ENDCHAR")?;
+        let mut words = line.split_whitespace();
+        match words.next() {
+            Some("ENCODING") => encoding = Some(bdf_ints::<1>(words, line)?[0]),
+            Some("DWIDTH") => advance = Some(bdf_ints::<2>(words, line)?[0]),
+            Some("BBX") => bbx = Some(bdf_ints::<4>(words, line)?),
+            Some("BITMAP") => break,
+            Some("ENDCHAR") => return Ok(None),
+            _ => {}
+        }
+    }
+
+    let [w, h, x_off, y_off] = bbx.context("BDF glyph has no BBX")?;
+    let (width, height) = (w.max(0) as u32, h.max(0) as u32);
+    let mut bits = Vec::with_capacity((width * height) as usize);
+    for _ in 0..height {
+        let row = lines.next().context("BDF glyph bitmap is truncated")?;
+        let digits = row
+            .chars()
+            .map(|c| c.to_digit(16))
+            .collect::<Option<Vec<_>>>()
+            .with_context(|| format!("bad BDF bitmap row '{row}'"))?;
+        bits.extend((0..width as usize).map(|i| {
+            digits
+                .get(i / 4)
+                .is_some_and(|d| d & (0b1000 >> (i % 4)) != 0)
+        }));
+    }
+    if lines.next() != Some("ENDCHAR") {
+        bail!("BDF glyph bitmap has extra rows");
+    }
+
+    let ch = encoding
+        .and_then(|e| u32::try_from(e).ok())
+        .and_then(char::from_u32);
+    Ok(ch.map(|ch| {
+        (
+            ch,
+            Glyph {
+                width,
+                height,
+                x_off,
+                y_off,
+                advance: advance.unwrap_or(w),
+                bits,
+            },
+        )
+    }))
+}
+
+fn bdf_ints<'a, const N: usize>(
+    mut words: impl Iterator<Item = &'a str>,
+    line: &str,
+) -> Result<[i32; N]> {
+    let mut out = [0; N];
+    for value in &mut out {
+        *value = words
+            .next()
+            .and_then(|w| w.parse().ok())
+            .with_context(|| format!("bad BDF line '{line}'"))?;
+    }
+    Ok(out)
+}
+
+/// A PCF table: its format word and the bytes after it.
+struct PcfTable<'a> {
+    format: u32,
+    data: &'a [u8],
+}
+
+impl PcfTable<'_> {
+    fn u8(&self, pos: usize) -> Result<u8> {
+        self.data
+            .get(pos)
+            .copied()
+            .context("unexpected end of PCF table")
+    }
+
+    fn u16(&self, pos: usize) -> Result<u16> {
+        let b = [self.u8(pos)?, self.u8(pos + 1)?];
+        Ok(if self.format & PCF_BYTE_MSB != 0 {
+            u16::from_be_bytes(b)
+        } else {
+            u16::from_le_bytes(b)
+        })
+    }
+
+    fn u32(&self, pos: usize) -> Result<u32> {
+        let b = [
+            self.u8(pos)?,
+            self.u8(pos + 1)?,
+            self.u8(pos + 2)?,
+            self.u8(pos + 3)?,
+        ];
+        Ok(if self.format & PCF_BYTE_MSB != 0 {
+            u32::from_be_bytes(b)
+        } else {
+            u32::from_le_bytes(b)
+        })
+    }
+}
+
+fn parse_pcf(data: &[u8]) -> Result<Font> {
+    if !data.starts_with(PCF_MAGIC) {
+        bail!("not a PCF font");
+    }
+    let le_u32 = |pos: usize| -> Result<u32> {
+        let b = data.get(pos..pos + 4).context("truncated PCF header")?;
+        Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    };
+    let mut tables = HashMap::new();
+    for i in 0..le_u32(4)? as usize {
+        let entry = 8 + i * 16;
+        let (kind, size, offset) = (
+            le_u32(entry)?,
+            le_u32(entry + 8)? as usize,
+            le_u32(entry + 12)? as usize,
+        );
+        let table = data
+            .get(offset..offset + size)
+            .filter(|t| t.len() >= 4)
+            .context("PCF table lies outside the file")?;
+        tables.insert(
+            kind,
+            PcfTable {
+                format: u32::from_le_bytes([table[0], table[1], table[2], table[3]]),
+                data: &table[4..],
+            },
+        );
+    }
+    let table = |kind: u32, name: &str| {
+        tables
+            .get(&kind)
+            .with_context(|| format!("PCF font has no {name} table"))
+    };
+
+    let metrics = table(PCF_METRICS, "metrics")?;
+    let mut glyph_metrics = Vec::new();
+    if metrics.format & PCF_COMPRESSED_METRICS != 0 {
+        for i in 0..metrics.u16(0)? as usize {
+            let m = |n: usize| -> Result<i32> { Ok(metrics.u8(2 + i * 5 + n)? as i32 - 0x80) };
+            glyph_metrics.push([m(0)?, m(1)?, m(2)?, m(3)?, m(4)?]);
+        }
+    } else {
+        for i in 0..metrics.u32(0)? as usize {
+            let m =
+                |n: usize| -> Result<i32> { Ok(metrics.u16(4 + i * 12 + n * 2)? as i16 as i32) };
+            glyph_metrics.push([m(0)?, m(1)?, m(2)?, m(3)?, m(4)?]);
+        }
+    }
+
+    let bitmaps = table(PCF_BITMAPS, "bitmaps")?;
+    let count = bitmaps.u32(0)? as usize;
+    if count != glyph_metrics.len() {
+        bail!("PCF bitmap and metrics tables disagree on the glyph count");
+    }
+    let data_start = 4 + count * 4 + 16;
+    let pad = 1usize << (bitmaps.format & 3);
+    let unit = 1usize << ((bitmaps.format >> 4) & 3);
+    let byte_msb = bitmaps.format & PCF_BYTE_MSB != 0;
+    let bit_msb = bitmaps.format & PCF_BIT_MSB != 0;
+
+    let mut glyphs_by_index = Vec::with_capacity(count);
+    for (i, [left, right, advance, ascent, descent]) in glyph_metrics.into_iter().enumerate() {
+        let width = (right - left).max(0) as u32;
+        let height = (ascent + descent).max(0) as u32;
+        let stride = (width as usize).div_ceil(8).div_ceil(pad) * pad;
+        let offset = data_start + bitmaps.u32(4 + i * 4)? as usize;
+        let mut bits = Vec::with_capacity((width * height) as usize);
+        for row in 0..height as usize {
+            let start = offset + row * stride;
+            let mut bytes = bitmaps
+                .data
+                .get(start..start + stride)
+                .context("PCF glyph bitmap lies outside its table")?
+                .to_vec();
+            // Scan units are stored in the byte order; bits are read in the bit order.
+            if byte_msb != bit_msb && unit > 1 {
+                for chunk in bytes.chunks_mut(unit) {
+                    chunk.reverse();
+                }
+            }
+            bits.extend((0..width as usize).map(|x| {
+                let byte = bytes[x / 8];
+                let bit = if bit_msb { 7 - x % 8 } else { x % 8 };
+                byte >> bit & 1 != 0
+            }));
+        }
+        glyphs_by_index.push(Glyph {
+            width,
+            height,
+            x_off: left,
+            y_off: -descent,
+            advance,
+            bits,
+        });
+    }
+
+    let encodings = table(PCF_BDF_ENCODINGS, "encodings")?;
+    let (min_b2, max_b2) = (encodings.u16(0)? as u32, encodings.u16(2)? as u32);
+    let (min_b1, max_b1) = (encodings.u16(4)? as u32, encodings.u16(6)? as u32);
+    let default_code = encodings.u16(8)? as u32;
+    let mut glyphs = HashMap::new();
+    let mut n = 0;
+    for b1 in min_b1..=max_b1 {
+        for b2 in min_b2..=max_b2 {
+            let idx = encodings.u16(10 + n * 2)?;
+            n += 1;
+            if idx == PCF_NO_GLYPH {
+                continue;
+            }
+            let glyph = glyphs_by_index.get(idx as usize);
+            if let (Some(ch), Some(glyph)) = (char::from_u32(b1 << 8 | b2), glyph) {
+                glyphs.insert(ch, glyph.clone());
+            }
+        }
+    }
+
+    let accel = table(PCF_BDF_ACCELERATORS, "accelerators")
+        .or_else(|_| table(PCF_ACCELERATORS, "accelerators"))?;
+    Ok(Font {
+        ascent: accel.u32(8)? as i32,
+        descent: accel.u32(12)? as i32,
+        glyphs,
+        default_char: char::from_u32(default_code),
+    })
+}
+
+/// A single row of equally wide glyph cells, one per character of `glyphs`.
+fn parse_strip(image: &RgbaImage, glyphs: &str) -> Result<Font> {
+    let chars = glyphs.chars().collect::<Vec<_>>();
+    if chars.is_empty() {
+        bail!("the glyphs map is empty");
+    }
+    let count = chars.len() as u32;
+    if !image.width().is_multiple_of(count) {
+        bail!(
+            "strip is {}px wide, which does not split into {count} equal glyphs",
+            image.width()
+        );
+    }
+    let (cell_w, cell_h) = (image.width() / count, image.height());
+
+    let mut map = HashMap::new();
+    for (n, ch) in chars.into_iter().enumerate() {
+        let x0 = n as u32 * cell_w;
+        let bits = (0..cell_h)
+            .flat_map(|y| (0..cell_w).map(move |x| (x, y)))
+            .map(|(x, y)| image.get_pixel(x0 + x, y)[3] >= STRIP_ALPHA_CUTOFF)
+            .collect();
+        map.insert(
+            ch,
+            Glyph {
+                width: cell_w,
+                height: cell_h,
+                x_off: 0,
+                y_off: 0,
+                advance: cell_w as i32,
+                bits,
+            },
+        );
+    }
+    Ok(Font {
+        ascent: cell_h as i32,
+        descent: 0,
+        glyphs: map,
+        default_char: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `A` as a 3x4 glyph: a peak, a bar and two legs.
+    const A_ROWS: [u8; 4] = [0b010, 0b101, 0b111, 0b101];
+
+    const BDF: &str = "STARTFONT 2.1
+FONTBOUNDINGBOX 4 5 0 -1
+FONT_ASCENT 4
+FONT_DESCENT 1
+DEFAULT_CHAR 65
+STARTCHAR A
+ENCODING 65
+DWIDTH 4 0
+BBX 3 4 0 0
+BITMAP
+40
+A0
+E0
+A0
+ENDCHAR
+STARTCHAR unencoded
+ENCODING -1
+DWIDTH 4 0
+BBX 1 1 0 0
+BITMAP
+80
+ENDCHAR
+ENDFONT
+";
+
+    /// The same font as `BDF`, as a PCF with every table in one byte/bit order and
+    /// bitmap rows padded to `1 << pad_log` bytes.
+    fn pcf(byte_msb: bool, bit_msb: bool, pad_log: u32) -> Vec<u8> {
+        let format = pad_log
+            | if byte_msb { PCF_BYTE_MSB } else { 0 }
+            | if bit_msb { PCF_BIT_MSB } else { 0 };
+        let u16 = |v: u16| match byte_msb {
+            true => v.to_be_bytes().to_vec(),
+            false => v.to_le_bytes().to_vec(),
+        };
+        let u32 = |v: u32| match byte_msb {
+            true => v.to_be_bytes().to_vec(),
+            false => v.to_le_bytes().to_vec(),
+        };
+
+        // left, right, advance, ascent, descent, attributes
+        let mut metrics = u32(1);
+        for v in [0i16, 3, 4, 4, 0, 0] {
+            metrics.extend(u16(v as u16));
+        }
+
+        let stride = 1usize << pad_log;
+        let mut rows = Vec::new();
+        for row in A_ROWS {
+            let mut bytes = vec![0u8; stride];
+            for x in 0..3 {
+                if row & (0b100 >> x) != 0 {
+                    bytes[0] |= if bit_msb { 0x80 >> x } else { 1 << x };
+                }
+            }
+            rows.extend(bytes);
+        }
+        let mut bitmaps = u32(1);
+        bitmaps.extend(u32(0));
+        for _ in 0..4 {
+            bitmaps.extend(u32(rows.len() as u32));
+        }
+        bitmaps.extend(rows);
+
+        // One row of encodings, 0x40..=0x41, with only `A` present.
+        let mut encodings = Vec::new();
+        for v in [0x40, 0x41, 0, 0, 0x41, PCF_NO_GLYPH, 0] {
+            encodings.extend(u16(v));
+        }
+
+        let mut accel = vec![0u8; 8];
+        accel.extend(u32(4));
+        accel.extend(u32(1));
+
+        let tables = [
+            (PCF_METRICS, metrics),
+            (PCF_BITMAPS, bitmaps),
+            (PCF_BDF_ENCODINGS, encodings),
+            (PCF_BDF_ACCELERATORS, accel),
+        ];
+        let mut out = PCF_MAGIC.to_vec();
+        out.extend((tables.len() as u32).to_le_bytes());
+        let mut offset = 8 + tables.len() * 16;
+        let mut body = Vec::new();
+        for (kind, data) in &tables {
+            let size = data.len() + 4;
+            for v in [*kind, format, size as u32, offset as u32] {
+                out.extend(v.to_le_bytes());
+            }
+            body.extend(format.to_le_bytes());
+            body.extend(data);
+            offset += size;
+        }
+        out.extend(body);
+        out
+    }
+
+    fn rendered(font: &Font, text: &str) -> RgbaImage {
+        let mut image = RgbaImage::new(10, 6);
+        font.draw(&mut image, text, 1, 1, 1, Rgba([255, 255, 255, 255]));
+        image
+    }
+
+    fn assert_draws_a(font: &Font) {
+        let image = rendered(font, "A");
+        for (y, row) in A_ROWS.iter().enumerate() {
+            for x in 0..3 {
+                let inked = image.get_pixel(1 + x, 1 + y as u32)[3] != 0;
+                assert_eq!(inked, row & (0b100 >> x) != 0, "pixel {x},{y}");
+            }
+        }
+        assert_eq!(image.pixels().filter(|p| p[3] != 0).count(), 8);
+        assert_eq!(font.measure("AA", 2), (14, 10));
+    }
+
+    #[test]
+    fn decodes_bdf_glyphs() {
+        let font = parse_bdf(BDF).unwrap();
+        assert_eq!(font.glyphs.len(), 1);
+        assert_eq!(font.default_char, Some('A'));
+        assert_draws_a(&font);
+        // Missing characters draw the default glyph.
+        assert_eq!(rendered(&font, "?"), rendered(&font, "A"));
+    }
+
+    #[test]
+    fn decodes_pcf_in_every_bit_order() {
+        for byte_msb in [false, true] {
+            for bit_msb in [false, true] {
+                for pad_log in [0, 2] {
+                    let font = parse_pcf(&pcf(byte_msb, bit_msb, pad_log)).unwrap();
+                    assert_eq!((font.ascent, font.descent), (4, 1));
+                    assert_eq!(font.glyphs.len(), 1);
+                    assert_draws_a(&font);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_fonts() {
+        assert!(parse_bdf("FONT nope").is_err());
+        assert!(parse_bdf(&BDF.replace("A0\nENDCHAR", "A0\nA0\nENDCHAR")).is_err());
+        let bytes = pcf(false, false, 0);
+        assert!(parse_pcf(&bytes[..bytes.len() - 8]).is_err());
+        assert!(parse_pcf(b"\x01fcq").is_err());
+    }
+
+    #[test]
+    fn fallback_covers_latin1() {
+        let font = Font::fallback();
+        assert!(font.glyph('é').is_some());
+        assert_eq!(font.measure("ab", 1).1, 8);
+    }
+}
@@ -1,18 +1,33 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::fs;
-use std::io::{ErrorKind, Write};
+use std::io::{Cursor, ErrorKind, Write};
 use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
 
 use anyhow::{bail, Context, Result};
 use clap::{Parser, Subcommand};
 use globset::{Glob, GlobSet, GlobSetBuilder};
-use image::imageops::FilterType;
-use image::{DynamicImage, GenericImage, Rgba, RgbaImage};
-use serde::Deserialize;
+use image::imageops::{self, FilterType};
+use image::{DynamicImage, GenericImage, ImageFormat, Rgba, RgbaImage};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::{Digest, Sha256};
 use walkdir::WalkDir;
 use zip::write::FileOptions;
 use zip::{CompressionMethod, DateTime, ZipWriter};
 
+mod anim;
+mod aseprite;
+mod atlas;
+mod engines;
+mod font;
+mod scene;
+mod site;
+mod tty;
+
 #[derive(Parser, Debug)]
 #[command(name = "welder")]
 #[command(about = "Turn raw pixel art into ship-ready asset packs.", long_about = None)]
@@ -26,6 +41,10 @@ struct Cli {
     #[arg(short, long, action = clap::ArgAction::Count)]
     verbose: u8,
 
+    /// Worker threads for image processing (default: available CPUs)
+    #[arg(short = 'j', long, global = true)]
+    jobs: Option<usize>,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -60,11 +79,14 @@ enum Commands {
         res: Option<String>,
         #[arg(long)]
         clean: bool,
+        /// Ignore the build manifest and re-export every sprite
+        #[arg(long)]
+        force: bool,
         #[arg(long)]
         dry_run: bool,
     },
 
-    /// Generate preview images (sheet/grid)
+    /// Generate preview images (sheet/grid/anim/cover/screenshots/scene/site)
     Preview {
         #[arg(long, default_value = "default")]
         profile: String,
@@ -72,6 +94,36 @@ enum Commands {
         style: String,
         #[arg(long)]
         dry_run: bool,
+        /// Draw the sheet/grid in the terminal instead of writing files: auto, kitty, sixel
+        /// or blocks
+        #[arg(long, value_name = "MODE", num_args = 0..=1, default_missing_value = "auto")]
+        tty: Option<String>,
+        /// With --tty, draw this sprite (path under paths.input) instead
+        #[arg(long, requires = "tty")]
+        sprite: Option<String>,
+    },
+
+    /// Pack unwatermarked texture atlases into paths.sheets
+    Atlas {
+        #[arg(long, default_value = "default")]
+        profile: String,
+        #[arg(long)]
+        res: Option<String>,
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Write engine resources (Godot, ...) for the packed atlases into paths.engines
+    Export {
+        #[arg(long, default_value = "default")]
+        profile: String,
+        #[arg(long)]
+        res: Option<String>,
+        /// Engine to export for; repeatable (default: export.engines)
+        #[arg(long = "engine")]
+        engines: Vec<String>,
+        #[arg(long)]
+        dry_run: bool,
     },
 
     /// Create a versioned zip in dist/package/
@@ -109,6 +161,9 @@ struct Config {
     grid: GridConfig,
     metadata: Option<MetadataConfig>,
     publish: Option<PublishConfig>,
+    palettes: Option<PalettesConfig>,
+    export: Option<ExportConfig>,
+    animations: Option<AnimationsConfig>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -128,13 +183,31 @@ struct Paths {
     previews: PathBuf,
     exports: PathBuf,
     sheets: Option<PathBuf>,
+    /// Engine exporter output; defaults to `<dist>/engines`.
+    engines: Option<PathBuf>,
     package: PathBuf,
 }
 
+impl Paths {
+    fn engines_dir(&self) -> PathBuf {
+        self.engines
+            .clone()
+            .unwrap_or_else(|| self.dist.join("engines"))
+    }
+}
+
 #[derive(Debug, Deserialize)]
 struct Inputs {
     include: Vec<String>,
     exclude: Vec<String>,
+    aseprite: Option<AsepriteInputConfig>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct AsepriteInputConfig {
+    include_layers: Option<Vec<String>>,
+    #[serde(default)]
+    exclude_layers: Vec<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -147,9 +220,98 @@ struct BuildConfig {
 #[derive(Debug, Deserialize)]
 struct PreviewConfig {
     styles: Vec<String>,
-    background: String,
+    background: BackgroundConfig,
+    /// Watermark text, grid labels and titles (default `#ffffff`).
+    text_color: Option<String>,
+    /// Extra variants of every preview, written with a `-<theme>` suffix.
+    #[serde(default)]
+    themes: BTreeMap<String, ThemeConfig>,
     scale: Option<u32>,
+    /// What `padding_px`, `cell_px` and watermark sizes are measured in: `source` pixels
+    /// (multiplied by `scale`, the default) or final `output` pixels.
+    units: Option<String>,
     watermark: Option<WatermarkConfig>,
+    anim: Option<AnimPreviewConfig>,
+    font: Option<FontConfig>,
+    cover: Option<CoverConfig>,
+    screenshots: Option<ScreenshotsConfig>,
+    scene: Option<SceneConfig>,
+}
+
+/// `preview.background`: a `#RRGGBB` / `#RRGGBBAA` color, `"checker"`, or a table for a
+/// checkerboard, gradient or tiled PNG.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum BackgroundConfig {
+    Color(String),
+    Pattern(BackgroundPattern),
+}
+
+#[derive(Debug, Deserialize)]
+struct BackgroundPattern {
+    /// Checkerboard cell size, in `preview.units`.
+    checker: Option<u32>,
+    /// `vertical` (top to bottom) or `horizontal` (left to right).
+    gradient: Option<String>,
+    /// PNG repeated from the top-left corner, upscaled like the art.
+    image: Option<PathBuf>,
+    /// The two checker colors, or two or more evenly spaced gradient stops.
+    #[serde(default)]
+    colors: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ThemeConfig {
+    background: BackgroundConfig,
+    /// Defaults to `preview.text_color`.
+    text_color: Option<String>,
+}
+
+/// `cover` style: itch.io's 630x500 cover and its 315x250 thumbnail.
+#[derive(Debug, Default, Deserialize)]
+struct CoverConfig {
+    /// Globs picking the sprites to show; all sprites when empty.
+    #[serde(default)]
+    sprites: Vec<String>,
+    /// Defaults to `pack.name`; an empty string leaves the title out.
+    title: Option<String>,
+    /// Size of a title font pixel on the 630x500 cover (default 4).
+    title_scale: Option<u32>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct ScreenshotsConfig {
+    /// Globs picking the sprites to show; all sprites when empty.
+    #[serde(default)]
+    sprites: Vec<String>,
+    /// `WIDTHxHEIGHT` images to write (default 1280x720 and 1920x1080).
+    sizes: Option<Vec<String>>,
+}
+
+/// `scene` style: demo maps rendered from the pack's sprites.
+#[derive(Debug, Default, Deserialize)]
+struct SceneConfig {
+    /// Tiled `.tmx` maps or `.toml` / `.csv` layouts; each writes `scenes/<stem>.png`.
+    #[serde(default)]
+    maps: Vec<PathBuf>,
+}
+
+/// Font for watermarks and other preview text; the bundled 5x8 Latin-1 font is used
+/// when unset and for characters the font lacks.
+#[derive(Debug, Deserialize)]
+struct FontConfig {
+    /// `.bdf`, `.pcf` or `.png` strip of equally wide glyphs.
+    path: PathBuf,
+    /// Characters of a PNG strip, left to right.
+    glyphs: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct AnimPreviewConfig {
+    /// `each` (one file per animation) or `showcase` (all animations in one grid).
+    mode: Option<String>,
+    /// Any of `gif` and `apng`.
+    formats: Option<Vec<String>>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -159,6 +321,18 @@ struct WatermarkConfig {
     opacity: Option<f32>,
     position: Option<String>,
     margin_px: Option<u32>,
+    /// Size of a font pixel, in `preview.units` (default 2).
+    glyph_scale: Option<u32>,
+    /// PNG logo drawn instead of the text.
+    logo: Option<PathBuf>,
+    /// Size of a logo pixel, in `preview.units` (default 1).
+    logo_scale: Option<u32>,
+    /// `single` (default, placed at `position`) or `tile` (repeated diagonally).
+    mode: Option<String>,
+    /// Tile mode: counter-clockwise rotation of the rows, in degrees (default 30).
+    angle: Option<f32>,
+    /// Tile mode: gap between repeats, in `preview.units` (default 32).
+    spacing_px: Option<u32>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -167,6 +341,8 @@ struct SheetConfig {
     max_height: u32,
     padding_px: u32,
     sort: Option<String>,
+    /// Atlas metadata layout: `json-hash` (default) or `json-array`.
+    format: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -174,6 +350,93 @@ struct GridConfig {
     cell_px: u32,
     padding_px: u32,
     columns: u32,
+    /// Draw each sprite's file stem under its cell.
+    labels: Option<bool>,
+    /// Label font pixel size, in `preview.units` (default 1); section headers are twice it.
+    label_scale: Option<u32>,
+    /// Group sprites under a header per top-level input folder.
+    sections: Option<bool>,
+    /// Split into `grid-NN.png` pages past this height, in output pixels (default 4096).
+    page_height_px: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PalettesConfig {
+    /// Name of the palette in `sets` that source art is drawn with.
+    source: String,
+    /// Palettes to emit a recolored export tree for.
+    targets: Vec<String>,
+    sets: BTreeMap<String, PaletteSet>,
+}
+
+/// A list of colors maps by index onto the source palette; a table maps exact
+/// `"#src" = "#dst"` colors and leaves unlisted source colors untouched.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum PaletteSet {
+    Indexed(Vec<String>),
+    Mapped(BTreeMap<String, String>),
+}
+
+#[derive(Debug, Deserialize)]
+struct AnimationsConfig {
+    /// Sprite name pattern with `{anim}` and `{frame}` placeholders (default `{anim}_{frame}`).
+    pattern: Option<String>,
+    /// Regex with named `anim` and `frame` groups, instead of `pattern`.
+    regex: Option<String>,
+    frame_ms: Option<u32>,
+    #[serde(rename = "loop")]
+    looped: Option<bool>,
+    /// Per-animation settings keyed by animation name (`"hero/walk"`).
+    #[serde(default)]
+    overrides: BTreeMap<String, AnimationOverride>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AnimationOverride {
+    frame_ms: Option<u32>,
+    #[serde(rename = "loop")]
+    looped: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ExportConfig {
+    /// Engines written by `welder export` and added to the package.
+    #[serde(default)]
+    engines: Vec<String>,
+    unity: Option<UnityExportConfig>,
+    tiles: Option<TilesExportConfig>,
+    rpgmaker: Option<RpgMakerExportConfig>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RpgMakerExportConfig {
+    /// Integer upscale to RPG Maker's 48px grid; inferred from tileset tiles when unset.
+    scale: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TilesExportConfig {
+    /// Sprites that are tiles (Tiled/LDtk tilesets); empty or unset means every sprite.
+    #[serde(default)]
+    include: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct UnityExportConfig {
+    pixels_per_unit: Option<UnityPixelsPerUnit>,
+    /// Also write every sprite as its own Single-mode PNG under `sprites/`.
+    #[serde(default)]
+    loose_sprites: bool,
+}
+
+/// `16` applies to 1x and scales with the resolution; `{ "1x" = 16, "2x" = 32 }` sets
+/// each resolution explicitly.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum UnityPixelsPerUnit {
+    Base(f32),
+    PerResolution(BTreeMap<String, f32>),
 }
 
 #[derive(Debug, Deserialize)]
@@ -214,6 +477,11 @@ fn run() -> Result<()> {
     }
 
     let config_path = PathBuf::from(&cli.config);
+    let jobs = match cli.jobs {
+        Some(0) => bail!("--jobs must be > 0"),
+        Some(jobs) => jobs,
+        None => thread::available_parallelism().map_or(1, |n| n.get()),
+    };
 
     match cli.command {
         Commands::Init {
@@ -225,31 +493,71 @@ fn run() -> Result<()> {
         } => run_init(&config_path, name, author, brand, input, yes),
         Commands::Doctor { butler } => run_doctor(&config_path, butler),
         Commands::Build {
-            profile: _,
+            profile,
             res,
             clean,
+            force,
             dry_run,
-        } => run_build(&config_path, res, clean, dry_run),
+        } => {
+            let cfg = load_profile_config(&config_path, &profile, cli.verbose)?;
+            run_build(&cfg, res, clean, force, dry_run, jobs)
+        }
         Commands::Preview {
-            profile: _,
+            profile,
             style,
             dry_run,
-        } => run_preview(&config_path, &style, dry_run),
+            tty,
+            sprite,
+        } => {
+            let cfg = load_profile_config(&config_path, &profile, cli.verbose)?;
+            match tty {
+                Some(mode) => run_preview_tty(&cfg, &style, &mode, sprite.as_deref(), jobs),
+                None => run_preview(&cfg, &style, dry_run, jobs),
+            }
+        }
+        Commands::Atlas {
+            profile,
+            res,
+            dry_run,
+        } => {
+            let cfg = load_profile_config(&config_path, &profile, cli.verbose)?;
+            run_atlas(&cfg, res, dry_run, jobs)
+        }
+        Commands::Export {
+            profile,
+            res,
+            engines,
+            dry_run,
+        } => {
+            let cfg = load_profile_config(&config_path, &profile, cli.verbose)?;
+            run_export(&cfg, res, engines, dry_run, jobs)
+        }
         Commands::Package {
-            profile: _,
+            profile,
             out,
             include_previews,
         } => {
+            let cfg = load_profile_config(&config_path, &profile, cli.verbose)?;
             let out = out.map(PathBuf::from);
-            run_package(&config_path, out, include_previews).map(|_| ())
+            run_package_with_config(&cfg, out, include_previews).map(|_| ())
         }
         Commands::Publish {
-            profile: _,
+            profile,
             channel,
             dry_run,
             yes: _,
-        } => run_publish(&config_path, channel, dry_run),
+        } => {
+            let cfg = load_profile_config(&config_path, &profile, cli.verbose)?;
+            run_publish(&cfg, channel, dry_run)
+        }
+    }
+}
+
+fn load_profile_config(config_path: &Path, profile: &str, verbose: u8) -> Result<Config> {
+    if verbose > 0 {
+        eprintln!("profile: {profile}");
     }
+    load_config(config_path, profile)
 }
 
 fn run_init(
@@ -277,14 +585,15 @@ fn run_init(
         println!("exists  {}", config_path.display());
     }
 
-    let cfg = load_config(config_path)?;
-    let dirs = [
+    let cfg = load_config(config_path, DEFAULT_PROFILE)?;
+    let mut dirs = vec![
         cfg.paths.input.clone(),
         cfg.paths.dist.clone(),
         cfg.paths.previews.clone(),
         cfg.paths.exports.clone(),
         cfg.paths.package.clone(),
     ];
+    dirs.extend(cfg.paths.sheets.clone());
 
     for dir in dirs {
         fs::create_dir_all(&dir).with_context(|| format!("failed creating {}", dir.display()))?;
@@ -297,7 +606,7 @@ fn run_init(
 fn run_doctor(config_path: &Path, only_butler: bool) -> Result<()> {
     if only_butler {
         let butler_bin = if config_path.exists() {
-            load_config(config_path)
+            load_config(config_path, DEFAULT_PROFILE)
                 .ok()
                 .and_then(|cfg| cfg.publish)
                 .and_then(|p| p.itch)
@@ -311,11 +620,26 @@ fn run_doctor(config_path: &Path, only_butler: bool) -> Result<()> {
         return Ok(());
     }
 
-    let cfg = load_config(config_path)?;
+    let cfg = load_config(config_path, DEFAULT_PROFILE)?;
     let mut issues = Vec::new();
 
     validate_config(&cfg, &mut issues);
 
+    for profile in list_profiles(config_path)? {
+        match load_config(config_path, &profile) {
+            Ok(profile_cfg) => {
+                let mut profile_issues = Vec::new();
+                validate_config(&profile_cfg, &mut profile_issues);
+                issues.extend(
+                    profile_issues
+                        .into_iter()
+                        .map(|issue| format!("profile '{profile}': {issue}")),
+                );
+            }
+            Err(err) => issues.push(format!("profile '{profile}': {err:#}")),
+        }
+    }
+
     let checked_paths = [
         (&cfg.paths.input, true),
         (&cfg.paths.dist, false),
@@ -360,8 +684,14 @@ fn run_doctor(config_path: &Path, only_butler: bool) -> Result<()> {
     bail!("doctor failed")
 }
 
-fn run_build(config_path: &Path, res: Option<String>, clean: bool, dry_run: bool) -> Result<()> {
-    let cfg = load_config(config_path)?;
+fn run_build(
+    cfg: &Config,
+    res: Option<String>,
+    clean: bool,
+    force: bool,
+    dry_run: bool,
+    jobs: usize,
+) -> Result<()> {
     let resolutions = parse_resolutions(res.as_deref(), &cfg.build.resolutions)?;
 
     if clean && cfg.paths.dist.exists() {
@@ -380,22 +710,200 @@ fn run_build(config_path: &Path, res: Option<String>, clean: bool, dry_run: bool
             .with_context(|| format!("failed creating {}", cfg.paths.exports.display()))?;
     }
 
-    let input_files = collect_input_pngs(&cfg)?;
+    let input_files = collect_input_pngs(cfg)?;
     if input_files.is_empty() {
         println!("no matching PNG files found");
-        return Ok(());
     }
 
-    for file in &input_files {
-        let in_path = cfg.paths.input.join(file);
-        let img = image::open(&in_path)
-            .with_context(|| format!("failed reading image {}", in_path.display()))?;
+    let manifest_path = cfg.paths.dist.join(BUILD_MANIFEST);
+    let previous = read_build_manifest(&manifest_path)?;
+    let mut manifest = BuildManifest {
+        version: BUILD_MANIFEST_VERSION,
+        config_hash: build_config_hash(cfg),
+        sources: BTreeMap::new(),
+    };
+    let reuse = !force && previous.config_hash == manifest.config_hash;
 
-        for factor in &resolutions {
-            let out_path = cfg.paths.exports.join(format!("{factor}x")).join(file);
+    let trim = cfg.build.trim_transparent.unwrap_or(false);
+    let mut exported = 0usize;
+    let mut up_to_date = 0usize;
 
-            if dry_run {
+    let plan = BuildPlan {
+        layer_rules: aseprite_layer_rules(cfg)?,
+        variants: palette_variants(cfg)?,
+        resolutions,
+        dry_run,
+    };
+    // Dry runs print every planned write, so keep them serial for readable output.
+    let jobs = if dry_run { 1 } else { jobs };
+    let results = par_map(jobs, &input_files, |file| {
+        let cached = previous
+            .sources
+            .get(&normalize_for_glob(file))
+            .filter(|_| reuse);
+        build_source(cfg, &plan, file, cached)
+    });
+
+    for (file, result) in input_files.iter().zip(results) {
+        let (entry, rebuilt) = result?;
+        let source = cfg.paths.input.join(file);
+        for sprite in &entry.transparent {
+            if *sprite == normalize_for_glob(file) {
+                eprintln!(
+                    "warning: {} is fully transparent, skipping",
+                    source.display()
+                );
+            } else {
+                eprintln!(
+                    "warning: {sprite} (from {}) is fully transparent, skipping",
+                    source.display()
+                );
+            }
+        }
+        if rebuilt {
+            exported += 1;
+        } else {
+            up_to_date += 1;
+        }
+        manifest.sources.insert(normalize_for_glob(file), entry);
+    }
+
+    ensure_unique_sprites(manifest.sources.iter().flat_map(|(source, entry)| {
+        entry
+            .sprites
+            .keys()
+            .map(move |s| (s.as_str(), source.as_str()))
+    }))?;
+
+    let mut removed = remove_stale_exports(cfg, &previous, &manifest, dry_run)?;
+    removed += remove_stale_trim_sidecars(cfg, &plan, trim, dry_run)?;
+
+    if trim {
+        let trims = manifest
+            .sources
+            .values()
+            .flat_map(|entry| &entry.sprites)
+            .filter_map(|(name, trim)| trim.map(|rect| (name.clone(), rect)))
+            .collect::<BTreeMap<_, _>>();
+        let trees =
+            std::iter::once(None).chain(plan.variants.iter().map(|v| Some(v.name.as_str())));
+        for variant in trees {
+            for factor in &plan.resolutions {
+                let out_path = cfg
+                    .paths
+                    .exports
+                    .join(export_key(variant, *factor, TRIM_SIDECAR));
+                if dry_run {
+                    println!("[dry-run] write {}", out_path.display());
+                    continue;
+                }
+                write_trim_sidecar(&out_path, &trims, *factor)?;
+            }
+        }
+    }
+
+    if dry_run {
+        println!("[dry-run] write {}", manifest_path.display());
+    } else {
+        write_build_manifest(&manifest_path, &manifest)?;
+    }
+
+    println!(
+        "build: exported {exported} source file(s), {up_to_date} up to date, removed {removed} stale file(s)"
+    );
+    Ok(())
+}
+
+/// Per-run settings shared by every `build_source` call.
+struct BuildPlan {
+    layer_rules: aseprite::LayerRules,
+    variants: Vec<PaletteVariant>,
+    resolutions: Vec<u32>,
+    dry_run: bool,
+}
+
+/// Exports every sprite of one source at every requested resolution, skipping the decode
+/// entirely when `cached` proves each output is still current. Returns the manifest entry
+/// and whether anything was re-encoded.
+fn build_source(
+    cfg: &Config,
+    plan: &BuildPlan,
+    file: &Path,
+    cached: Option<&ManifestSource>,
+) -> Result<(ManifestSource, bool)> {
+    let BuildPlan {
+        layer_rules,
+        variants,
+        resolutions,
+        dry_run,
+    } = plan;
+    let in_path = cfg.paths.input.join(file);
+    let bytes =
+        fs::read(&in_path).with_context(|| format!("failed reading {}", in_path.display()))?;
+    let hash = sha256_hex(&bytes);
+
+    let mut entry = ManifestSource {
+        hash,
+        sprites: BTreeMap::new(),
+        durations: BTreeMap::new(),
+        transparent: Vec::new(),
+        outputs: BTreeMap::new(),
+    };
+
+    if let Some(cached) = cached.filter(|c| c.hash == entry.hash) {
+        // Outputs of resolutions or palettes that are no longer built are left out, so
+        // `remove_stale_exports` deletes them.
+        let wanted = cached
+            .sprites
+            .keys()
+            .flat_map(|sprite| sprite_export_keys(variants, resolutions, sprite))
+            .map(|(_, _, key)| key)
+            .collect::<BTreeSet<_>>();
+        for (out, out_hash) in &cached.outputs {
+            if wanted.contains(out) && output_is_current(cfg, out, out_hash) {
+                entry.outputs.insert(out.clone(), out_hash.clone());
+            }
+        }
+        let complete = cached.sprites.keys().all(|sprite| {
+            sprite_export_keys(variants, resolutions, sprite)
+                .iter()
+                .all(|(_, _, key)| entry.outputs.contains_key(key))
+        });
+        if complete {
+            entry.sprites = cached.sprites.clone();
+            entry.durations = cached.durations.clone();
+            entry.transparent = cached.transparent.clone();
+            return Ok((entry, false));
+        }
+    }
+
+    let trim = cfg.build.trim_transparent.unwrap_or(false);
+    for (sprite_path, mut img, duration) in decode_source(cfg, layer_rules, file, &bytes)? {
+        let sprite = normalize_for_glob(&sprite_path);
+        let mut rect = None;
+        if trim {
+            let Some((trimmed, r)) = trim_sprite(&img) else {
+                entry.transparent.push(sprite);
+                continue;
+            };
+            img = trimmed;
+            rect = Some(r);
+        }
+        entry.sprites.insert(sprite.clone(), rect);
+        if let Some(ms) = duration {
+            entry.durations.insert(sprite.clone(), ms);
+        }
+
+        let mut recolored = BTreeMap::new();
+        for (variant, factor, key) in sprite_export_keys(variants, resolutions, &sprite) {
+            if entry.outputs.contains_key(&key) {
+                continue;
+            }
+            let out_path = cfg.paths.exports.join(&key);
+
+            if *dry_run {
                 println!("[dry-run] {} -> {}", in_path.display(), out_path.display());
+                entry.outputs.insert(key, String::new());
                 continue;
             }
 
@@ -404,33 +912,52 @@ fn run_build(config_path: &Path, res: Option<String>, clean: bool, dry_run: bool
                     .with_context(|| format!("failed creating {}", parent.display()))?;
             }
 
-            let scaled = if *factor == 1 {
-                img.clone()
+            let base = match variant {
+                None => &img,
+                Some(v) => match recolored.entry(v.name.as_str()) {
+                    std::collections::btree_map::Entry::Occupied(e) => e.into_mut(),
+                    std::collections::btree_map::Entry::Vacant(e) => e
+                        .insert(recolor_sprite(&img, v).with_context(|| {
+                            format!("palette '{}' failed for {sprite}", v.name)
+                        })?),
+                },
+            };
+            let scaled = if factor == 1 {
+                base.clone()
             } else {
-                img.resize_exact(
-                    img.width() * *factor,
-                    img.height() * *factor,
+                base.resize_exact(
+                    base.width() * factor,
+                    base.height() * factor,
                     FilterType::Nearest,
                 )
             };
 
+            let mut encoded = Vec::new();
             scaled
-                .save(&out_path)
+                .write_to(&mut Cursor::new(&mut encoded), ImageFormat::Png)
+                .with_context(|| format!("failed encoding image {}", out_path.display()))?;
+            fs::write(&out_path, &encoded)
                 .with_context(|| format!("failed writing image {}", out_path.display()))?;
+            entry.outputs.insert(key, sha256_hex(&encoded));
         }
     }
 
-    println!("build: exported {} source file(s)", input_files.len());
-    Ok(())
+    // Outputs carried over from the cache may belong to frames that no longer exist.
+    entry.outputs.retain(|key, _| {
+        export_key_sprite(key).is_some_and(|sprite| entry.sprites.contains_key(sprite))
+    });
+
+    Ok((entry, true))
 }
 
-fn run_preview(config_path: &Path, style: &str, dry_run: bool) -> Result<()> {
-    let cfg = load_config(config_path)?;
+fn run_preview(cfg: &Config, style: &str, dry_run: bool, jobs: usize) -> Result<()> {
     let styles = preview_styles(style, &cfg.preview.styles)?;
-    let sprites = load_sprites(&cfg)?;
+    let sprites = load_sprites(cfg, jobs)?;
     if sprites.is_empty() {
         bail!("no matching PNG files found for preview");
     }
+    let font = preview_font(cfg)?;
+    let themes = preview_themes(cfg)?;
 
     if dry_run {
         println!("[dry-run] create {}", cfg.paths.previews.display());
@@ -438,14 +965,113 @@ fn run_preview(config_path: &Path, style: &str, dry_run: bool) -> Result<()> {
         fs::create_dir_all(&cfg.paths.previews)
             .with_context(|| format!("failed creating {}", cfg.paths.previews.display()))?;
     }
+    for theme in &themes {
+        write_previews(cfg, &styles, theme, &font, &sprites, dry_run)?;
+    }
+    // The site shows the exports themselves, so it is written once rather than per theme.
+    if styles.iter().any(|s| s == "site") {
+        site::write(cfg, &sprites, dry_run)?;
+    }
+
+    println!("preview: generated {}", styles.join(", "));
+    Ok(())
+}
+
+/// Draws previews in the terminal instead of writing them: the sheet and/or grid as they
+/// would be saved (watermark included), or one sprite at `preview.scale`.
+fn run_preview_tty(
+    cfg: &Config,
+    style: &str,
+    mode: &str,
+    sprite: Option<&str>,
+    jobs: usize,
+) -> Result<()> {
+    let mode = tty::Mode::parse(mode)?;
+    let styles = preview_styles(style, &cfg.preview.styles)?;
+    if sprite.is_none() {
+        if let Some(other) = styles.iter().find(|s| *s != "sheet" && *s != "grid") {
+            bail!("--tty draws the sheet and grid styles or a --sprite, not '{other}'");
+        }
+    }
+    let sprites = load_sprites(cfg, jobs)?;
+    if sprites.is_empty() {
+        bail!("no matching PNG files found for preview");
+    }
+    let font = preview_font(cfg)?;
+    let theme = preview_themes(cfg)?.remove(0);
+    let mark = watermark_mark(cfg, &font, theme.text)?;
+
+    let mut images = Vec::new();
+    if let Some(name) = sprite {
+        let wanted = name.trim().trim_start_matches("./").replace('\\', "/");
+        let found = sprites
+            .iter()
+            .find(|s| {
+                let path = normalize_for_glob(&s.path);
+                path == wanted || path.strip_suffix(".png") == Some(wanted.as_str())
+            })
+            .with_context(|| format!("no sprite named '{name}'"))?;
+        let scale = preview_scale(cfg);
+        let (w, h) = (found.image.width() * scale, found.image.height() * scale);
+        let mut image = theme.background.canvas(w, h);
+        imageops::overlay(
+            &mut image,
+            &imageops::resize(&found.image, w, h, FilterType::Nearest),
+            0,
+            0,
+        );
+        images.push((normalize_for_glob(&found.path), image));
+    } else {
+        if styles.iter().any(|s| s == "sheet") {
+            let packed = prepare_atlas_sprites(cfg, sprites.clone());
+            let mut sheet = render_sheet(cfg, &theme, &packed)?;
+            apply_watermark(cfg, mark.as_ref(), &mut sheet);
+            images.push(("sheet".to_string(), sheet));
+        }
+        if styles.iter().any(|s| s == "grid") {
+            let pages = plan_grid(cfg, &theme, &font, &sprites).render(&theme);
+            let count = pages.len();
+            for (idx, mut page) in pages.into_iter().enumerate() {
+                apply_watermark(cfg, mark.as_ref(), &mut page);
+                let label = if count > 1 {
+                    format!("grid {}/{count}", idx + 1)
+                } else {
+                    "grid".to_string()
+                };
+                images.push((label, page));
+            }
+        }
+    }
+
+    let size = tty::size();
+    let mut out = std::io::stdout().lock();
+    for (label, image) in &images {
+        writeln!(out, "{label} ({}x{})", image.width(), image.height())?;
+        tty::show(&mut out, image, mode, size)?;
+    }
+    out.flush()?;
+    Ok(())
+}
+
+/// Writes every style in `styles` with `theme`'s colors and file name suffix.
+fn write_previews(
+    cfg: &Config,
+    styles: &[String],
+    theme: &Theme,
+    font: &font::Font,
+    sprites: &[Sprite],
+    dry_run: bool,
+) -> Result<()> {
+    let mark = watermark_mark(cfg, font, theme.text)?;
 
     if styles.iter().any(|s| s == "sheet") {
-        let out = cfg.paths.previews.join("sheet.png");
+        let out = cfg.paths.previews.join(theme.file("sheet", "png"));
         if dry_run {
             println!("[dry-run] write {}", out.display());
         } else {
-            let mut sheet = render_sheet(&cfg, &sprites)?;
-            apply_watermark(&cfg, &mut sheet);
+            let packed = prepare_atlas_sprites(cfg, sprites.to_vec());
+            let mut sheet = render_sheet(cfg, theme, &packed)?;
+            apply_watermark(cfg, mark.as_ref(), &mut sheet);
             sheet
                 .save(&out)
                 .with_context(|| format!("failed writing {}", out.display()))?;
@@ -453,97 +1079,346 @@ fn run_preview(config_path: &Path, style: &str, dry_run: bool) -> Result<()> {
     }
 
     if styles.iter().any(|s| s == "grid") {
-        let out = cfg.paths.previews.join("grid.png");
+        let plan = plan_grid(cfg, theme, font, sprites);
+        let names = grid_page_names(theme, plan.pages.len());
+        // A pack that shrank to one page must not leave old `grid-NN.png` pages behind.
+        if let Ok(entries) = fs::read_dir(&cfg.paths.previews) {
+            for entry in entries.flatten() {
+                let name = entry.file_name().to_string_lossy().into_owned();
+                if !is_grid_preview(&name, &theme.suffix) || names.contains(&name) {
+                    continue;
+                }
+                if dry_run {
+                    println!("[dry-run] remove {}", entry.path().display());
+                } else {
+                    fs::remove_file(entry.path())
+                        .with_context(|| format!("failed removing {}", entry.path().display()))?;
+                }
+            }
+        }
         if dry_run {
-            println!("[dry-run] write {}", out.display());
+            for name in &names {
+                println!(
+                    "[dry-run] write {}",
+                    cfg.paths.previews.join(name).display()
+                );
+            }
         } else {
-            let mut grid = render_grid(&cfg, &sprites)?;
-            apply_watermark(&cfg, &mut grid);
-            grid.save(&out)
-                .with_context(|| format!("failed writing {}", out.display()))?;
+            for (name, mut page) in names.iter().zip(plan.render(theme)) {
+                let out = cfg.paths.previews.join(name);
+                apply_watermark(cfg, mark.as_ref(), &mut page);
+                page.save(&out)
+                    .with_context(|| format!("failed writing {}", out.display()))?;
+            }
         }
     }
 
-    println!("preview: generated {}", styles.join(", "));
-    Ok(())
-}
+    if styles.iter().any(|s| s == "anim") {
+        write_anim_previews(cfg, theme, mark.as_ref(), sprites, dry_run)?;
+    }
 
-fn run_package(
-    config_path: &Path,
-    out_path: Option<PathBuf>,
-    include_previews: bool,
-) -> Result<PathBuf> {
-    let cfg = load_config(config_path)?;
-    run_package_with_config(&cfg, out_path, include_previews)
-}
+    if styles.iter().any(|s| s == "cover") {
+        let cover = cfg.preview.cover.as_ref();
+        let selected = select_sprites(sprites, cover.map_or(&[], |c| &c.sprites))
+            .context("preview.cover.sprites")?;
+        let title = cover
+            .and_then(|c| c.title.as_deref())
+            .unwrap_or(&cfg.pack.name)
+            .trim();
+        let title_scale = cover.and_then(|c| c.title_scale).unwrap_or(4).max(1);
+        for (name, size, scale) in [
+            ("cover", (630, 500), title_scale),
+            ("thumb", (315, 250), title_scale.div_ceil(2)),
+        ] {
+            let out = cfg.paths.previews.join(theme.file(name, "png"));
+            if dry_run {
+                println!("[dry-run] write {}", out.display());
+                continue;
+            }
+            let title = Some((title, scale)).filter(|(t, _)| !t.is_empty());
+            let mut image = render_showcase(cfg, theme, font, &selected, size, title)?;
+            apply_watermark(cfg, mark.as_ref(), &mut image);
+            image
+                .save(&out)
+                .with_context(|| format!("failed writing {}", out.display()))?;
+        }
+    }
 
-fn run_package_with_config(
-    cfg: &Config,
-    out_path: Option<PathBuf>,
-    include_previews: bool,
-) -> Result<PathBuf> {
-    if !cfg.paths.exports.exists() {
-        bail!(
-            "exports directory is missing at {} (run 'welder build' first)",
-            cfg.paths.exports.display()
-        );
+    if styles.iter().any(|s| s == "screenshots") {
+        let patterns = cfg
+            .preview
+            .screenshots
+            .as_ref()
+            .map_or(&[][..], |s| &s.sprites);
+        let selected = select_sprites(sprites, patterns).context("preview.screenshots.sprites")?;
+        let dir = cfg.paths.previews.join("screenshots");
+        if !dry_run {
+            fs::create_dir_all(&dir)
+                .with_context(|| format!("failed creating {}", dir.display()))?;
+        }
+        for (w, h) in screenshot_sizes(cfg)? {
+            let out = dir.join(theme.file(&format!("screenshot-{w}x{h}"), "png"));
+            if dry_run {
+                println!("[dry-run] write {}", out.display());
+                continue;
+            }
+            let mut image = render_showcase(cfg, theme, font, &selected, (w, h), None)?;
+            apply_watermark(cfg, mark.as_ref(), &mut image);
+            image
+                .save(&out)
+                .with_context(|| format!("failed writing {}", out.display()))?;
+        }
     }
 
-    fs::create_dir_all(&cfg.paths.package)
-        .with_context(|| format!("failed creating {}", cfg.paths.package.display()))?;
+    if styles.iter().any(|s| s == "scene") {
+        let dir = cfg.paths.previews.join("scenes");
+        if !dry_run {
+            fs::create_dir_all(&dir)
+                .with_context(|| format!("failed creating {}", dir.display()))?;
+        }
+        for (stem, map) in scene_maps(cfg)? {
+            let out = dir.join(theme.file(stem, "png"));
+            if dry_run {
+                println!("[dry-run] write {}", out.display());
+                continue;
+            }
+            let scene = scene::render(map, &cfg.paths.input, sprites)?;
+            let scale = preview_scale(cfg);
+            let (w, h) = (scene.width() * scale, scene.height() * scale);
+            let mut image = theme.background.canvas(w, h);
+            imageops::overlay(
+                &mut image,
+                &imageops::resize(&scene, w, h, FilterType::Nearest),
+                0,
+                0,
+            );
+            apply_watermark(cfg, mark.as_ref(), &mut image);
+            image
+                .save(&out)
+                .with_context(|| format!("failed writing {}", out.display()))?;
+        }
+    }
 
-    let out = out_path.unwrap_or_else(|| {
-        cfg.paths
-            .package
-            .join(format!("{}-{}.zip", cfg.pack.slug, cfg.pack.semver))
-    });
+    Ok(())
+}
 
-    if let Some(parent) = out.parent() {
-        fs::create_dir_all(parent)
-            .with_context(|| format!("failed creating {}", parent.display()))?;
+fn run_atlas(cfg: &Config, res: Option<String>, dry_run: bool, jobs: usize) -> Result<()> {
+    let sheets = cfg
+        .paths
+        .sheets
+        .as_ref()
+        .context("paths.sheets is not configured")?;
+    let resolutions = parse_resolutions(res.as_deref(), &cfg.build.resolutions)?;
+    let format = atlas_format(cfg)?;
+    let sprites = load_atlas_sprites(cfg, jobs)?;
+    if sprites.is_empty() {
+        bail!("no matching sprites found for atlas");
+    }
+    let animations = animation_groups(cfg, &sprites)?
+        .into_iter()
+        .map(|anim| {
+            let frames = anim
+                .frames
+                .iter()
+                .map(|&idx| normalize_for_glob(&sprites[idx].path))
+                .collect::<Vec<_>>();
+            (anim.name, frames)
+        })
+        .collect::<Vec<_>>();
+
+    // Pack every resolution before writing so a sprite that overflows at 4x does not
+    // leave freshly written 1x pages next to stale 4x ones.
+    let mut packed = Vec::with_capacity(resolutions.len());
+    for factor in &resolutions {
+        let dir = sheets.join(format!("{factor}x"));
+        packed.push((*factor, dir, build_atlas_pages(cfg, &sprites, *factor)?));
     }
 
-    let file =
-        fs::File::create(&out).with_context(|| format!("failed creating {}", out.display()))?;
-    let mut zip = ZipWriter::new(file);
-    let ts = DateTime::from_date_and_time(1980, 1, 1, 0, 0, 0)
-        .map_err(|_| anyhow::anyhow!("failed creating fixed ZIP timestamp"))?;
-
-    let file_opts = FileOptions::default()
-        .compression_method(CompressionMethod::Stored)
+    for (factor, dir, pages) in packed {
+        if dry_run {
+            println!(
+                "[dry-run] write {} page(s) to {}",
+                pages.len(),
+                dir.display()
+            );
+            continue;
+        }
+
+        // Pages are numbered, so clear old ones in case the page count shrank.
+        if dir.exists() {
+            fs::remove_dir_all(&dir)
+                .with_context(|| format!("failed removing {}", dir.display()))?;
+        }
+        fs::create_dir_all(&dir).with_context(|| format!("failed creating {}", dir.display()))?;
+
+        let json_names = (0..pages.len())
+            .map(|idx| {
+                Path::new(&atlas_page_name(idx))
+                    .with_extension("json")
+                    .to_string_lossy()
+                    .into_owned()
+            })
+            .collect::<Vec<_>>();
+        for (idx, page) in pages.iter().enumerate() {
+            let image_name = atlas_page_name(idx);
+            let out = dir.join(&image_name);
+            page.image
+                .save(&out)
+                .with_context(|| format!("failed writing {}", out.display()))?;
+
+            let related = json_names
+                .iter()
+                .enumerate()
+                .filter(|(other, _)| *other != idx)
+                .map(|(_, name)| name.clone())
+                .collect::<Vec<_>>();
+            let json = atlas_page_json(&format, page, &image_name, &related, &animations, factor);
+            let mut text =
+                serde_json::to_string_pretty(&json).context("failed encoding atlas metadata")?;
+            text.push('\n');
+            let json_out = dir.join(&json_names[idx]);
+            fs::write(&json_out, text)
+                .with_context(|| format!("failed writing {}", json_out.display()))?;
+        }
+    }
+
+    println!(
+        "atlas: packed {} sprite(s) at {} resolution(s)",
+        sprites.len(),
+        resolutions.len()
+    );
+    Ok(())
+}
+
+fn run_export(
+    cfg: &Config,
+    res: Option<String>,
+    engines: Vec<String>,
+    dry_run: bool,
+    jobs: usize,
+) -> Result<()> {
+    let mut engines = if engines.is_empty() {
+        configured_engines(cfg)
+    } else {
+        engines
+            .iter()
+            .map(|e| e.trim().to_ascii_lowercase())
+            .collect()
+    };
+    engines.sort();
+    engines.dedup();
+    if engines.is_empty() {
+        bail!("no engines selected (set export.engines or pass --engine)");
+    }
+    for engine in &engines {
+        engines::validate_engine(engine)?;
+    }
+
+    let resolutions = parse_resolutions(res.as_deref(), &cfg.build.resolutions)?;
+    let sprites = load_export_sprites(cfg, jobs)?;
+    if sprites.is_empty() {
+        bail!("no matching sprites found for export");
+    }
+    let animations = animation_groups(cfg, &sprites)?;
+    let mut packed = Vec::with_capacity(resolutions.len());
+    for factor in resolutions {
+        packed.push(engines::PackedResolution {
+            factor,
+            pages: build_atlas_pages(cfg, &sprites, factor)?,
+        });
+    }
+    let input = engines::ExportInput {
+        cfg,
+        sprites: &sprites,
+        resolutions: &packed,
+        animations: &animations,
+    };
+
+    let root = cfg.paths.engines_dir();
+    for engine in &engines {
+        let out = root.join(engine);
+        if dry_run {
+            println!("[dry-run] write {engine} resources to {}", out.display());
+            continue;
+        }
+        if out.exists() {
+            fs::remove_dir_all(&out)
+                .with_context(|| format!("failed removing {}", out.display()))?;
+        }
+        engines::export(engine, &input, &out).with_context(|| format!("{engine} export failed"))?;
+    }
+
+    println!(
+        "export: wrote {} for {} sprite(s), {} animation(s)",
+        engines.join(", "),
+        sprites.len(),
+        animations.len()
+    );
+    Ok(())
+}
+
+fn configured_engines(cfg: &Config) -> Vec<String> {
+    cfg.export
+        .as_ref()
+        .map(|e| e.engines.iter().map(|e| e.to_ascii_lowercase()).collect())
+        .unwrap_or_default()
+}
+
+fn run_package_with_config(
+    cfg: &Config,
+    out_path: Option<PathBuf>,
+    include_previews: bool,
+) -> Result<PathBuf> {
+    if !cfg.paths.exports.exists() {
+        bail!(
+            "exports directory is missing at {} (run 'welder build' first)",
+            cfg.paths.exports.display()
+        );
+    }
+
+    fs::create_dir_all(&cfg.paths.package)
+        .with_context(|| format!("failed creating {}", cfg.paths.package.display()))?;
+
+    let out = out_path.unwrap_or_else(|| {
+        cfg.paths
+            .package
+            .join(format!("{}-{}.zip", cfg.pack.slug, cfg.pack.semver))
+    });
+
+    if let Some(parent) = out.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("failed creating {}", parent.display()))?;
+    }
+
+    let file =
+        fs::File::create(&out).with_context(|| format!("failed creating {}", out.display()))?;
+    let mut zip = ZipWriter::new(file);
+    let ts = DateTime::from_date_and_time(1980, 1, 1, 0, 0, 0)
+        .map_err(|_| anyhow::anyhow!("failed creating fixed ZIP timestamp"))?;
+
+    let file_opts = FileOptions::default()
+        .compression_method(CompressionMethod::Stored)
         .last_modified_time(ts)
         .unix_permissions(0o644);
 
-    let mut export_files = collect_files_sorted(&cfg.paths.exports)?;
-    for file in export_files.drain(..) {
-        let rel = file
-            .strip_prefix(&cfg.paths.exports)
-            .with_context(|| format!("failed to relativize {}", file.display()))?;
-        let zip_path = format!("exports/{}", normalize_for_glob(rel));
-        let bytes =
-            fs::read(&file).with_context(|| format!("failed reading {}", file.display()))?;
-        zip.start_file(zip_path, file_opts)
-            .context("failed starting zip file entry")?;
-        zip.write_all(&bytes)
-            .context("failed writing zip file entry")?;
+    zip_dir(&mut zip, &cfg.paths.exports, "exports", file_opts)?;
+
+    if let Some(sheets) = cfg.paths.sheets.as_ref().filter(|p| p.exists()) {
+        zip_dir(&mut zip, sheets, "sheets", file_opts)?;
     }
 
-    if include_previews && cfg.paths.previews.exists() {
-        let mut preview_files = collect_files_sorted(&cfg.paths.previews)?;
-        for file in preview_files.drain(..) {
-            let rel = file
-                .strip_prefix(&cfg.paths.previews)
-                .with_context(|| format!("failed to relativize {}", file.display()))?;
-            let zip_path = format!("previews/{}", normalize_for_glob(rel));
-            let bytes =
-                fs::read(&file).with_context(|| format!("failed reading {}", file.display()))?;
-            zip.start_file(zip_path, file_opts)
-                .context("failed starting zip file entry")?;
-            zip.write_all(&bytes)
-                .context("failed writing zip file entry")?;
+    let engines_dir = cfg.paths.engines_dir();
+    for engine in configured_engines(cfg) {
+        let dir = engines_dir.join(&engine);
+        if dir.exists() {
+            zip_dir(&mut zip, &dir, &engine, file_opts)?;
         }
     }
 
+    if include_previews && cfg.paths.previews.exists() {
+        zip_dir(&mut zip, &cfg.paths.previews, "previews", file_opts)?;
+    }
+
     if let Some(readme) = generate_readme_if_configured(cfg)? {
         zip.start_file("README.md", file_opts)
             .context("failed starting README entry")?;
@@ -557,8 +1432,29 @@ fn run_package_with_config(
     Ok(out)
 }
 
-fn run_publish(config_path: &Path, channel_override: Option<String>, dry_run: bool) -> Result<()> {
-    let cfg = load_config(config_path)?;
+/// Adds every file under `root` to the zip as `<prefix>/<relative path>`, in sorted order.
+fn zip_dir(
+    zip: &mut ZipWriter<fs::File>,
+    root: &Path,
+    prefix: &str,
+    file_opts: FileOptions,
+) -> Result<()> {
+    for file in collect_files_sorted(root)? {
+        let rel = file
+            .strip_prefix(root)
+            .with_context(|| format!("failed to relativize {}", file.display()))?;
+        let zip_path = format!("{prefix}/{}", normalize_for_glob(rel));
+        let bytes =
+            fs::read(&file).with_context(|| format!("failed reading {}", file.display()))?;
+        zip.start_file(zip_path, file_opts)
+            .context("failed starting zip file entry")?;
+        zip.write_all(&bytes)
+            .context("failed writing zip file entry")?;
+    }
+    Ok(())
+}
+
+fn run_publish(cfg: &Config, channel_override: Option<String>, dry_run: bool) -> Result<()> {
     let itch = cfg
         .publish
         .as_ref()
@@ -569,7 +1465,7 @@ fn run_publish(config_path: &Path, channel_override: Option<String>, dry_run: bo
         bail!("publish.itch.enabled is false");
     }
 
-    let package_path = run_package_with_config(&cfg, None, false)?;
+    let package_path = run_package_with_config(cfg, None, false)?;
     let butler_bin = itch.butler_bin.as_deref().unwrap_or("butler");
     let channel = channel_override.unwrap_or_else(|| itch.channel.clone());
     let target = format!("{}/{}:{channel}", itch.user, itch.project);
@@ -604,12 +1500,83 @@ fn run_publish(config_path: &Path, channel_override: Option<String>, dry_run: bo
     Ok(())
 }
 
-fn load_config(path: &Path) -> Result<Config> {
+/// Profile used when `--profile` is not given. It only applies overrides if
+/// `[profiles.default]` exists.
+const DEFAULT_PROFILE: &str = "default";
+
+fn load_config(path: &Path, profile: &str) -> Result<Config> {
+    let mut root = read_config_table(path)?;
+    let profiles = root.remove("profiles");
+
+    let overrides = match profiles {
+        Some(toml::Value::Table(mut profiles)) => match profiles.remove(profile) {
+            Some(toml::Value::Table(overrides)) => Some(overrides),
+            Some(_) => bail!("profiles.{profile} must be a table"),
+            None if profile == DEFAULT_PROFILE => None,
+            None => {
+                let known = profiles.keys().cloned().collect::<Vec<_>>();
+                bail!(
+                    "profile '{profile}' not found in {} (available: {})",
+                    path.display(),
+                    if known.is_empty() {
+                        "none".to_string()
+                    } else {
+                        known.join(", ")
+                    }
+                );
+            }
+        },
+        Some(_) => bail!("profiles must be a table of [profiles.<name>] sections"),
+        None if profile == DEFAULT_PROFILE => None,
+        None => bail!(
+            "profile '{profile}' not found in {} (no [profiles] defined)",
+            path.display()
+        ),
+    };
+
+    if let Some(overrides) = overrides {
+        if overrides.contains_key("profiles") {
+            bail!("profiles.{profile} must not define nested profiles");
+        }
+        merge_toml_tables(&mut root, overrides);
+    }
+
+    let cfg: Config = toml::Value::Table(root).try_into().with_context(|| {
+        format!(
+            "failed parsing config {} (profile '{profile}')",
+            path.display()
+        )
+    })?;
+    Ok(cfg)
+}
+
+fn read_config_table(path: &Path) -> Result<toml::Table> {
     let content = fs::read_to_string(path)
         .with_context(|| format!("failed reading config {}", path.display()))?;
-    let cfg: Config = toml::from_str(&content)
-        .with_context(|| format!("failed parsing TOML from {}", path.display()))?;
-    Ok(cfg)
+    toml::from_str(&content).with_context(|| format!("failed parsing TOML from {}", path.display()))
+}
+
+fn list_profiles(path: &Path) -> Result<Vec<String>> {
+    let root = read_config_table(path)?;
+    Ok(match root.get("profiles") {
+        Some(toml::Value::Table(profiles)) => profiles.keys().cloned().collect(),
+        _ => Vec::new(),
+    })
+}
+
+/// Deep-merges `overrides` into `base`: tables merge key by key, every other value
+/// (including arrays) replaces the base value outright.
+fn merge_toml_tables(base: &mut toml::Table, overrides: toml::Table) {
+    for (key, value) in overrides {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(base_table)), toml::Value::Table(override_table)) => {
+                merge_toml_tables(base_table, override_table);
+            }
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
 }
 
 fn validate_config(cfg: &Config, issues: &mut Vec<String>) {
@@ -646,6 +1613,122 @@ fn validate_config(cfg: &Config, issues: &mut Vec<String>) {
     if cfg.grid.cell_px == 0 {
         issues.push("grid.cell_px must be > 0".to_string());
     }
+    if cfg.grid.label_scale == Some(0) {
+        issues.push("grid.label_scale must be > 0".to_string());
+    }
+    if cfg.grid.page_height_px == Some(0) {
+        issues.push("grid.page_height_px must be > 0".to_string());
+    }
+    if cfg.preview.scale == Some(0) {
+        issues.push("preview.scale must be > 0".to_string());
+    }
+    if cfg.preview.font.is_some() {
+        if let Err(err) = preview_font(cfg) {
+            issues.push(format!("preview.font: {err:#}"));
+        }
+    }
+    if let Err(err) = preview_themes(cfg) {
+        issues.push(format!("{err:#}"));
+    }
+    if let Some(wm) = &cfg.preview.watermark {
+        if let Some(mode) = &wm.mode {
+            if !["single", "tile"]
+                .iter()
+                .any(|m| mode.eq_ignore_ascii_case(m))
+            {
+                issues.push("preview.watermark.mode must be one of: single, tile".to_string());
+            }
+        }
+        if wm.glyph_scale == Some(0) {
+            issues.push("preview.watermark.glyph_scale must be > 0".to_string());
+        }
+        if wm.logo_scale == Some(0) {
+            issues.push("preview.watermark.logo_scale must be > 0".to_string());
+        }
+        if wm.angle.is_some_and(|a| !a.is_finite()) {
+            issues.push("preview.watermark.angle must be a number of degrees".to_string());
+        }
+        if let Some(logo) = wm.logo.as_ref().filter(|_| wm.enabled) {
+            if let Err(err) = image::open(logo) {
+                issues.push(format!(
+                    "preview.watermark.logo: failed reading {}: {err}",
+                    logo.display()
+                ));
+            }
+        }
+    }
+    if let Some(units) = &cfg.preview.units {
+        if !["source", "output"]
+            .iter()
+            .any(|u| units.eq_ignore_ascii_case(u))
+        {
+            issues.push("preview.units must be one of: source, output".to_string());
+        }
+    }
+    if let Err(err) = anim_preview_settings(cfg) {
+        issues.push(format!("{err:#}"));
+    }
+    if let Err(err) = screenshot_sizes(cfg) {
+        issues.push(format!("{err:#}"));
+    }
+    if let Some(cover) = &cfg.preview.cover {
+        if cover.title_scale == Some(0) {
+            issues.push("preview.cover.title_scale must be > 0".to_string());
+        }
+        if let Err(err) = build_globset(&cover.sprites) {
+            issues.push(format!("preview.cover.sprites: {err:#}"));
+        }
+    }
+    if let Some(screenshots) = &cfg.preview.screenshots {
+        if let Err(err) = build_globset(&screenshots.sprites) {
+            issues.push(format!("preview.screenshots.sprites: {err:#}"));
+        }
+    }
+    let scene_style = cfg.preview.styles.iter().any(|s| s == "scene");
+    if scene_style || cfg.preview.scene.is_some() {
+        match scene_maps(cfg) {
+            Ok(maps) => {
+                for (_, map) in maps {
+                    if !map.is_file() {
+                        issues.push(format!("preview.scene.maps: {} not found", map.display()));
+                    }
+                }
+            }
+            Err(err) => issues.push(format!("{err:#}")),
+        }
+    }
+    if let Some(sort) = &cfg.sheet.sort {
+        if !["name", "area", "height", "width"]
+            .iter()
+            .any(|s| sort.eq_ignore_ascii_case(s))
+        {
+            issues.push("sheet.sort must be one of: name, area, height, width".to_string());
+        }
+    }
+    if let Err(err) = palette_variants(cfg) {
+        issues.push(format!("palettes: {err:#}"));
+    }
+    if let Err(err) = atlas_format(cfg) {
+        issues.push(format!("{err:#}"));
+    }
+    if let Err(err) = animation_regex(cfg) {
+        issues.push(format!("{err:#}"));
+    }
+    if let Some(anims) = &cfg.animations {
+        let overrides = anims.overrides.values().map(|o| o.frame_ms);
+        if std::iter::once(anims.frame_ms)
+            .chain(overrides)
+            .any(|ms| ms == Some(0))
+        {
+            issues.push("animations frame_ms must be > 0".to_string());
+        }
+    }
+    for engine in configured_engines(cfg) {
+        if let Err(err) = engines::validate_engine(&engine) {
+            issues.push(format!("export.engines: {err:#}"));
+        }
+    }
+    engines::validate_config(cfg, issues);
     if let Some(filter) = &cfg.build.filter {
         if !filter.eq_ignore_ascii_case("nearest") {
             issues.push("build.filter must be 'nearest' for MVP".to_string());
@@ -675,7 +1758,7 @@ fn ensure_butler_available(bin: &str) -> Result<()> {
 
 fn starter_config(name: &str, slug: &str, author: &str, brand: &str, input: &str) -> String {
     format!(
-        "version = 1\n\n[pack]\nname = \"{name}\"\nslug = \"{slug}\"\nauthor = \"{author}\"\nbrand = \"{brand}\"\nlicense = \"CC0-1.0\"\nsemver = \"0.1.0\"\n\n[paths]\ninput = \"{input}\"\ndist = \"dist\"\npreviews = \"dist/previews\"\nexports = \"dist/exports\"\nsheets  = \"dist/sheets\"\npackage = \"dist/package\"\n\n[inputs]\ninclude = [\"**/*.png\"]\nexclude = [\"**/_wip/**\", \"**/.trash/**\"]\n\n[build]\nresolutions = [1, 2, 4]\nfilter = \"nearest\"\ntrim_transparent = true\n\n[preview]\nstyles = [\"sheet\", \"grid\"]\nbackground = \"#141414\"\nscale = 2\n\n[preview.watermark]\nenabled = true\ntext = \"iamkaf\"\nopacity = 0.12\nposition = \"bottom-right\"\nmargin_px = 12\n\n[sheet]\nmax_width = 2048\nmax_height = 2048\npadding_px = 2\nsort = \"name\"\n\n[grid]\ncell_px = 64\npadding_px = 8\ncolumns = 8\n\n[metadata]\nreadme_template = \"templates/README.md.tmpl\"\nitch_template = \"templates/ITCH.md.tmpl\"\n\n[publish.itch]\nenabled = true\nuser = \"{author}\"\nproject = \"{slug}\"\nchannel = \"default\"\nbutler_bin = \"butler\"\n"
+        "version = 1\n\n[pack]\nname = \"{name}\"\nslug = \"{slug}\"\nauthor = \"{author}\"\nbrand = \"{brand}\"\nlicense = \"CC0-1.0\"\nsemver = \"0.1.0\"\n\n[paths]\ninput = \"{input}\"\ndist = \"dist\"\npreviews = \"dist/previews\"\nexports = \"dist/exports\"\nsheets  = \"dist/sheets\"\npackage = \"dist/package\"\n\n[inputs]\ninclude = [\"**/*.png\", \"**/*.aseprite\", \"**/*.ase\"]\nexclude = [\"**/_wip/**\", \"**/.trash/**\"]\n\n[build]\nresolutions = [1, 2, 4]\nfilter = \"nearest\"\ntrim_transparent = true\n\n[preview]\nstyles = [\"sheet\", \"grid\"]\nbackground = \"#141414\"\nscale = 2\n\n[preview.watermark]\nenabled = true\ntext = \"iamkaf\"\nopacity = 0.12\nposition = \"bottom-right\"\nmargin_px = 12\n\n[sheet]\nmax_width = 2048\nmax_height = 2048\npadding_px = 2\nsort = \"name\"\n\n[grid]\ncell_px = 64\npadding_px = 8\ncolumns = 8\n\n[metadata]\nreadme_template = \"templates/README.md.tmpl\"\nitch_template = \"templates/ITCH.md.tmpl\"\n\n[publish.itch]\nenabled = true\nuser = \"{author}\"\nproject = \"{slug}\"\nchannel = \"default\"\nbutler_bin = \"butler\"\n"
     )
 }
 
@@ -738,7 +1821,7 @@ fn collect_input_pngs(cfg: &Config) -> Result<Vec<PathBuf>> {
         let ext = abs
             .extension()
             .and_then(|e| e.to_str())
-            .map(|e| e.eq_ignore_ascii_case("png"))
+            .map(|e| e.eq_ignore_ascii_case("png") || is_aseprite_ext(e))
             .unwrap_or(false);
         if !ext {
             continue;
@@ -810,206 +1893,1986 @@ fn preview_styles(style_arg: &str, config_default: &[String]) -> Result<Vec<Stri
     styles.dedup();
 
     for style in &styles {
-        if style != "sheet" && style != "grid" {
+        if ![
+            "sheet",
+            "grid",
+            "anim",
+            "cover",
+            "screenshots",
+            "scene",
+            "site",
+        ]
+        .contains(&style.as_str())
+        {
             bail!("unsupported preview style '{style}'");
         }
     }
     Ok(styles)
 }
 
-fn load_sprites(cfg: &Config) -> Result<Vec<(PathBuf, DynamicImage)>> {
-    let files = collect_input_pngs(cfg)?;
-    let mut sprites = Vec::with_capacity(files.len());
-    for file in files {
-        let abs = cfg.paths.input.join(&file);
-        let img = image::open(&abs).with_context(|| format!("failed reading {}", abs.display()))?;
-        sprites.push((file, img));
-    }
-    Ok(sprites)
-}
+/// Incremental build state, stored under `paths.dist`.
+const BUILD_MANIFEST: &str = "build-manifest.json";
+const BUILD_MANIFEST_VERSION: u32 = 3;
 
-fn render_sheet(cfg: &Config, sprites: &[(PathBuf, DynamicImage)]) -> Result<RgbaImage> {
-    let mut placements = Vec::with_capacity(sprites.len());
-    let mut x = cfg.sheet.padding_px;
-    let mut y = cfg.sheet.padding_px;
-    let mut row_h = 0u32;
-    let mut max_x = 0u32;
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct BuildManifest {
+    version: u32,
+    /// Hash of the config values that affect export bytes. A mismatch invalidates every entry.
+    config_hash: String,
+    /// Keyed by source path relative to `paths.input`.
+    sources: BTreeMap<String, ManifestSource>,
+}
 
-    for (_, img) in sprites {
-        let w = img.width();
-        let h = img.height();
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ManifestSource {
+    hash: String,
+    /// Sprites produced by this source (one per PNG, one per frame for Aseprite files),
+    /// with the trim applied to each when `build.trim_transparent` is on.
+    sprites: BTreeMap<String, Option<TrimRect>>,
+    /// Aseprite frame durations in milliseconds, keyed by sprite.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    durations: BTreeMap<String, u32>,
+    /// Sprites dropped because they are fully transparent.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    transparent: Vec<String>,
+    /// Output path relative to `paths.exports` -> hash of the written bytes.
+    outputs: BTreeMap<String, String>,
+}
 
-        if x > cfg.sheet.padding_px && x + w + cfg.sheet.padding_px > cfg.sheet.max_width {
-            x = cfg.sheet.padding_px;
-            y = y.saturating_add(row_h).saturating_add(cfg.sheet.padding_px);
-            row_h = 0;
+fn read_build_manifest(path: &Path) -> Result<BuildManifest> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(BuildManifest::default()),
+        Err(err) => {
+            return Err(err).with_context(|| format!("failed reading {}", path.display()));
         }
-
-        if y + h + cfg.sheet.padding_px > cfg.sheet.max_height {
-            bail!(
-                "sheet overflow: sprites exceed sheet.max_height ({})",
-                cfg.sheet.max_height
+    };
+    match serde_json::from_str::<BuildManifest>(&content) {
+        Ok(manifest) if manifest.version == BUILD_MANIFEST_VERSION => Ok(manifest),
+        _ => {
+            eprintln!(
+                "warning: ignoring unreadable build manifest {}",
+                path.display()
             );
+            Ok(BuildManifest::default())
         }
-
-        placements.push((x, y));
-        max_x = max_x.max(x + w + cfg.sheet.padding_px);
-        row_h = row_h.max(h);
-        x = x.saturating_add(w).saturating_add(cfg.sheet.padding_px);
-    }
-
-    let height = if sprites.is_empty() {
-        cfg.sheet.padding_px.saturating_mul(2).max(1)
-    } else {
-        y.saturating_add(row_h)
-            .saturating_add(cfg.sheet.padding_px)
-            .max(1)
-    };
-    let width = max_x.max(cfg.sheet.padding_px.saturating_mul(2)).max(1);
-    let bg = parse_hex_color(&cfg.preview.background)?;
-    let mut canvas = RgbaImage::from_pixel(width, height, bg);
-
-    for ((_, img), (px, py)) in sprites.iter().zip(placements) {
-        canvas
-            .copy_from(&img.to_rgba8(), px, py)
-            .context("failed placing sprite in sheet")?;
     }
-
-    Ok(canvas)
 }
 
-fn render_grid(cfg: &Config, sprites: &[(PathBuf, DynamicImage)]) -> Result<RgbaImage> {
-    let cell = cfg.grid.cell_px.max(1);
-    let pad = cfg.grid.padding_px;
-    let cols = cfg.grid.columns.max(1);
-    let rows = ((sprites.len() as u32) + cols - 1) / cols;
-    let width = cols
-        .saturating_mul(cell)
-        .saturating_add((cols + 1).saturating_mul(pad))
-        .max(1);
-    let height = rows
-        .saturating_mul(cell)
-        .saturating_add((rows + 1).saturating_mul(pad))
-        .max(1);
-    let bg = parse_hex_color(&cfg.preview.background)?;
-    let mut canvas = RgbaImage::from_pixel(width, height, bg);
-
-    for (idx, (_, img)) in sprites.iter().enumerate() {
-        let i = idx as u32;
-        let col = i % cols;
-        let row = i / cols;
-        let x0 = pad + col.saturating_mul(cell + pad);
-        let y0 = pad + row.saturating_mul(cell + pad);
-        let thumb = fit_in_cell(img, cell);
-        let ox = x0 + (cell - thumb.width()) / 2;
-        let oy = y0 + (cell - thumb.height()) / 2;
-        canvas
-            .copy_from(&thumb, ox, oy)
-            .context("failed placing sprite in grid")?;
+fn write_build_manifest(path: &Path, manifest: &BuildManifest) -> Result<()> {
+    let mut json =
+        serde_json::to_string_pretty(manifest).context("failed encoding build manifest")?;
+    json.push('\n');
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("failed creating {}", parent.display()))?;
     }
+    fs::write(path, json).with_context(|| format!("failed writing {}", path.display()))
+}
 
-    Ok(canvas)
+fn build_config_hash(cfg: &Config) -> String {
+    let trim = cfg.build.trim_transparent.unwrap_or(false);
+    let filter = cfg.build.filter.as_deref().unwrap_or("nearest");
+    let layers = cfg
+        .inputs
+        .aseprite
+        .as_ref()
+        .map(|a| format!("{:?}/{:?}", a.include_layers, a.exclude_layers))
+        .unwrap_or_default();
+    let palettes = format!("{:?}", cfg.palettes);
+    sha256_hex(
+        format!(
+            "trim_transparent={trim}\nfilter={filter}\naseprite_layers={layers}\npalettes={palettes}\n"
+        )
+        .as_bytes(),
+    )
 }
 
-fn fit_in_cell(img: &DynamicImage, cell_px: u32) -> RgbaImage {
-    let w = img.width().max(1);
-    let h = img.height().max(1);
-    let scale = (cell_px as f32 / w as f32).min(cell_px as f32 / h as f32);
-    let new_w = ((w as f32 * scale).floor() as u32).clamp(1, cell_px);
-    let new_h = ((h as f32 * scale).floor() as u32).clamp(1, cell_px);
-    img.resize_exact(new_w, new_h, FilterType::Nearest)
-        .to_rgba8()
+fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
 }
 
-fn parse_hex_color(s: &str) -> Result<Rgba<u8>> {
-    let value = s.trim().trim_start_matches('#');
-    if value.len() != 6 {
-        bail!("preview.background must be #RRGGBB, got '{s}'");
+/// Output path relative to `paths.exports`: `<res>x/<sprite>` for the base art and
+/// `<variant>/<res>x/<sprite>` for palette variants.
+fn export_key(variant: Option<&str>, factor: u32, sprite: &str) -> String {
+    match variant {
+        Some(variant) => format!("{variant}/{factor}x/{sprite}"),
+        None => format!("{factor}x/{sprite}"),
     }
-    let r = u8::from_str_radix(&value[0..2], 16).with_context(|| format!("bad red in '{s}'"))?;
-    let g = u8::from_str_radix(&value[2..4], 16).with_context(|| format!("bad green in '{s}'"))?;
-    let b = u8::from_str_radix(&value[4..6], 16).with_context(|| format!("bad blue in '{s}'"))?;
-    Ok(Rgba([r, g, b, 255]))
 }
 
-fn apply_watermark(cfg: &Config, image: &mut RgbaImage) {
-    let wm = match cfg.preview.watermark.as_ref() {
-        Some(wm) if wm.enabled => wm,
-        _ => return,
-    };
-
-    let text = wm
-        .text
-        .as_deref()
-        .map(str::trim)
-        .filter(|s| !s.is_empty())
-        .unwrap_or("iamkaf");
-    let opacity = wm.opacity.unwrap_or(0.12).clamp(0.0, 1.0);
-    let margin = wm.margin_px.unwrap_or(12);
-    let position = wm.position.as_deref().unwrap_or("bottom-right");
-    draw_bitmap_text(image, text, opacity, position, margin);
+/// Inverse of `export_key`: the sprite part of an output path.
+fn export_key_sprite(key: &str) -> Option<&str> {
+    let (first, rest) = key.split_once('/')?;
+    if is_resolution_dir(first) {
+        Some(rest)
+    } else {
+        rest.split_once('/').map(|(_, sprite)| sprite)
+    }
 }
 
-fn draw_bitmap_text(image: &mut RgbaImage, text: &str, opacity: f32, position: &str, margin: u32) {
-    let scale = 2u32;
-    let glyph_w = 5u32;
-    let glyph_h = 7u32;
-    let spacing = 1u32;
-    let chars: Vec<char> = text.to_ascii_uppercase().chars().collect();
-    if chars.is_empty() {
-        return;
-    }
+fn is_resolution_dir(name: &str) -> bool {
+    name.strip_suffix('x')
+        .is_some_and(|n| !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()))
+}
 
-    let text_w = (chars.len() as u32)
-        .saturating_mul(glyph_w + spacing)
-        .saturating_sub(spacing)
-        .saturating_mul(scale);
-    let text_h = glyph_h.saturating_mul(scale);
+fn sprite_export_keys<'a>(
+    variants: &'a [PaletteVariant],
+    resolutions: &[u32],
+    sprite: &str,
+) -> Vec<(Option<&'a PaletteVariant>, u32, String)> {
+    let trees = std::iter::once(None).chain(variants.iter().map(Some));
+    trees
+        .flat_map(|variant| {
+            resolutions.iter().map(move |factor| {
+                let key = export_key(variant.map(|v| v.name.as_str()), *factor, sprite);
+                (variant, *factor, key)
+            })
+        })
+        .collect()
+}
 
-    let (mut x, y) = match position.to_ascii_lowercase().as_str() {
-        "top-left" | "tl" => (margin, margin),
-        "top-right" | "tr" => (image.width().saturating_sub(text_w + margin), margin),
-        "bottom-left" | "bl" => (margin, image.height().saturating_sub(text_h + margin)),
-        "center" => (
-            (image.width().saturating_sub(text_w)) / 2,
-            (image.height().saturating_sub(text_h)) / 2,
-        ),
-        _ => (
-            image.width().saturating_sub(text_w + margin),
-            image.height().saturating_sub(text_h + margin),
-        ),
-    };
+fn output_is_current(cfg: &Config, key: &str, hash: &str) -> bool {
+    fs::read(cfg.paths.exports.join(key))
+        .map(|bytes| sha256_hex(&bytes) == hash)
+        .unwrap_or(false)
+}
 
-    let alpha = (opacity * 255.0).round().clamp(0.0, 255.0) as u8;
-    for ch in chars {
-        draw_glyph(image, ch, x, y, scale, alpha);
-        x = x.saturating_add((glyph_w + spacing).saturating_mul(scale));
+/// Deletes outputs recorded by the previous build that the current build no longer
+/// produces (deleted or excluded sources, or outdated resolutions of changed sources).
+fn remove_stale_exports(
+    cfg: &Config,
+    previous: &BuildManifest,
+    current: &BuildManifest,
+    dry_run: bool,
+) -> Result<usize> {
+    let mut removed = 0usize;
+    for (source, entry) in &previous.sources {
+        let kept = current.sources.get(source);
+        for out in entry.outputs.keys() {
+            if kept.is_some_and(|k| k.outputs.contains_key(out)) {
+                continue;
+            }
+            let path = cfg.paths.exports.join(out);
+            if !path.exists() {
+                continue;
+            }
+            removed += 1;
+            if dry_run {
+                println!("[dry-run] remove stale {}", path.display());
+                continue;
+            }
+            fs::remove_file(&path)
+                .with_context(|| format!("failed removing {}", path.display()))?;
+            prune_empty_dirs(path.parent(), &cfg.paths.exports);
+        }
     }
+    Ok(removed)
 }
 
-fn draw_glyph(image: &mut RgbaImage, ch: char, x: u32, y: u32, scale: u32, alpha: u8) {
-    let pattern = glyph_pattern(ch);
-    for (row, bits) in pattern.iter().enumerate() {
-        for (col, bit) in bits.chars().enumerate() {
-            if bit != '1' {
-                continue;
+/// Deletes `trim.json` sidecars of resolutions or palettes that are no longer built, or all
+/// of them once trimming is off; they are not tracked in the manifest.
+fn remove_stale_trim_sidecars(
+    cfg: &Config,
+    plan: &BuildPlan,
+    trim: bool,
+    dry_run: bool,
+) -> Result<usize> {
+    let mut wanted = BTreeSet::new();
+    if trim {
+        let trees =
+            std::iter::once(None).chain(plan.variants.iter().map(|v| Some(v.name.as_str())));
+        for variant in trees {
+            for factor in &plan.resolutions {
+                wanted.insert(
+                    cfg.paths
+                        .exports
+                        .join(export_key(variant, *factor, TRIM_SIDECAR)),
+                );
             }
-            for dy in 0..scale {
-                for dx in 0..scale {
-                    let px = x + col as u32 * scale + dx;
-                    let py = y + row as u32 * scale + dy;
-                    if px >= image.width() || py >= image.height() {
-                        continue;
-                    }
-                    blend_pixel(image, px, py, Rgba([255, 255, 255, alpha]));
+        }
+    }
+    // Sidecars live in `<res>x/` or `<variant>/<res>x/`.
+    let mut found = Vec::new();
+    for entry in read_dir_sorted(&cfg.paths.exports)? {
+        let name = entry
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or_default();
+        if is_resolution_dir(name) {
+            found.push(entry.join(TRIM_SIDECAR));
+        } else if entry.is_dir() {
+            for sub in read_dir_sorted(&entry)? {
+                if sub
+                    .file_name()
+                    .and_then(|n| n.to_str())
+                    .is_some_and(is_resolution_dir)
+                {
+                    found.push(sub.join(TRIM_SIDECAR));
                 }
             }
         }
     }
+
+    let mut removed = 0usize;
+    for path in found {
+        if wanted.contains(&path) || !path.is_file() {
+            continue;
+        }
+        removed += 1;
+        if dry_run {
+            println!("[dry-run] remove stale {}", path.display());
+            continue;
+        }
+        fs::remove_file(&path).with_context(|| format!("failed removing {}", path.display()))?;
+        prune_empty_dirs(path.parent(), &cfg.paths.exports);
+    }
+    Ok(removed)
 }
 
-fn blend_pixel(image: &mut RgbaImage, x: u32, y: u32, src: Rgba<u8>) {
-    let dst = image.get_pixel_mut(x, y);
+fn read_dir_sorted(dir: &Path) -> Result<Vec<PathBuf>> {
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut paths = fs::read_dir(dir)
+        .with_context(|| format!("failed reading {}", dir.display()))?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<std::io::Result<Vec<_>>>()
+        .with_context(|| format!("failed reading {}", dir.display()))?;
+    paths.sort();
+    Ok(paths)
+}
+
+fn prune_empty_dirs(mut dir: Option<&Path>, root: &Path) {
+    while let Some(d) = dir {
+        if d == root || !d.starts_with(root) || fs::remove_dir(d).is_err() {
+            break;
+        }
+        dir = d.parent();
+    }
+}
+
+/// A resolved `[palettes]` target: every source palette color mapped to its replacement.
+struct PaletteVariant {
+    name: String,
+    source: String,
+    mapping: HashMap<[u8; 3], [u8; 3]>,
+}
+
+fn palette_variants(cfg: &Config) -> Result<Vec<PaletteVariant>> {
+    let Some(palettes) = cfg.palettes.as_ref() else {
+        return Ok(Vec::new());
+    };
+    let source = match palettes.sets.get(&palettes.source) {
+        Some(PaletteSet::Indexed(colors)) => colors
+            .iter()
+            .map(|c| parse_palette_color(c, &palettes.source))
+            .collect::<Result<Vec<_>>>()?,
+        Some(PaletteSet::Mapped(_)) => bail!(
+            "palettes.sets.{} is the source palette and must be a list of colors",
+            palettes.source
+        ),
+        None => bail!(
+            "palettes.source '{}' is not defined in palettes.sets",
+            palettes.source
+        ),
+    };
+
+    let mut variants = Vec::with_capacity(palettes.targets.len());
+    for name in &palettes.targets {
+        if name.is_empty() || name.contains(['/', '\\']) || is_resolution_dir(name) {
+            bail!("palette target name '{name}' cannot be used as an export folder");
+        }
+        let mut mapping = source.iter().map(|c| (*c, *c)).collect::<HashMap<_, _>>();
+        match palettes.sets.get(name) {
+            Some(PaletteSet::Indexed(colors)) => {
+                if colors.len() != source.len() {
+                    bail!(
+                        "palette '{name}' has {} colors but source palette '{}' has {}",
+                        colors.len(),
+                        palettes.source,
+                        source.len()
+                    );
+                }
+                for (from, to) in source.iter().zip(colors) {
+                    let to = parse_palette_color(to, name)?;
+                    if mapping
+                        .get(from)
+                        .is_some_and(|prev| *prev != *from && *prev != to)
+                    {
+                        bail!(
+                            "palette '{name}' maps duplicate source color {} to different colors",
+                            hex_rgb(*from)
+                        );
+                    }
+                    mapping.insert(*from, to);
+                }
+            }
+            Some(PaletteSet::Mapped(pairs)) => {
+                for (from, to) in pairs {
+                    let from = parse_palette_color(from, name)?;
+                    if !source.contains(&from) {
+                        bail!(
+                            "palette '{name}' maps {} which is not in source palette '{}'",
+                            hex_rgb(from),
+                            palettes.source
+                        );
+                    }
+                    mapping.insert(from, parse_palette_color(to, name)?);
+                }
+            }
+            None => bail!("palette target '{name}' is not defined in palettes.sets"),
+        }
+        variants.push(PaletteVariant {
+            name: name.clone(),
+            source: palettes.source.clone(),
+            mapping,
+        });
+    }
+    Ok(variants)
+}
+
+fn parse_palette_color(value: &str, palette: &str) -> Result<[u8; 3]> {
+    let rgba = parse_hex_color(value).with_context(|| format!("in palette '{palette}'"))?;
+    if rgba[3] != 255 {
+        bail!("palette '{palette}': colors must be opaque #RRGGBB, got '{value}'");
+    }
+    Ok([rgba[0], rgba[1], rgba[2]])
+}
+
+fn hex_rgb(rgb: [u8; 3]) -> String {
+    format!("#{:02x}{:02x}{:02x}", rgb[0], rgb[1], rgb[2])
+}
+
+/// Swaps every visible pixel's color through `variant`, keeping alpha. Fails on the
+/// first color that is not part of the source palette.
+fn recolor_sprite(img: &DynamicImage, variant: &PaletteVariant) -> Result<DynamicImage> {
+    let mut rgba = img.to_rgba8();
+    for (x, y, px) in rgba.enumerate_pixels_mut() {
+        if px[3] == 0 {
+            continue;
+        }
+        let from = [px[0], px[1], px[2]];
+        let Some(to) = variant.mapping.get(&from) else {
+            bail!(
+                "pixel ({x}, {y}) has color {} which is not in source palette '{}'",
+                hex_rgb(from),
+                variant.source
+            );
+        };
+        *px = Rgba([to[0], to[1], to[2], px[3]]);
+    }
+    Ok(DynamicImage::ImageRgba8(rgba))
+}
+
+/// Sidecar written next to each resolution's exports when `build.trim_transparent` is on.
+const TRIM_SIDECAR: &str = "trim.json";
+
+/// Where a trimmed sprite sits inside its original, untrimmed canvas.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+struct TrimRect {
+    source_w: u32,
+    source_h: u32,
+    x: u32,
+    y: u32,
+    w: u32,
+    h: u32,
+}
+
+impl TrimRect {
+    fn scaled(self, factor: u32) -> Self {
+        Self {
+            source_w: self.source_w * factor,
+            source_h: self.source_h * factor,
+            x: self.x * factor,
+            y: self.y * factor,
+            w: self.w * factor,
+            h: self.h * factor,
+        }
+    }
+}
+
+/// Crops `img` to its opaque bounding box. Returns `None` when every pixel is transparent.
+fn trim_sprite(img: &DynamicImage) -> Option<(DynamicImage, TrimRect)> {
+    let rgba = img.to_rgba8();
+    let (mut min_x, mut min_y) = (u32::MAX, u32::MAX);
+    let (mut max_x, mut max_y) = (0u32, 0u32);
+    for (x, y, px) in rgba.enumerate_pixels() {
+        if px[3] == 0 {
+            continue;
+        }
+        min_x = min_x.min(x);
+        min_y = min_y.min(y);
+        max_x = max_x.max(x);
+        max_y = max_y.max(y);
+    }
+    if min_x == u32::MAX {
+        return None;
+    }
+
+    let rect = TrimRect {
+        source_w: img.width(),
+        source_h: img.height(),
+        x: min_x,
+        y: min_y,
+        w: max_x - min_x + 1,
+        h: max_y - min_y + 1,
+    };
+    Some((img.crop_imm(rect.x, rect.y, rect.w, rect.h), rect))
+}
+
+fn write_trim_sidecar(path: &Path, trims: &BTreeMap<String, TrimRect>, factor: u32) -> Result<()> {
+    let scaled = trims
+        .iter()
+        .map(|(name, rect)| (name.as_str(), rect.scaled(factor)))
+        .collect::<BTreeMap<_, _>>();
+    let mut json = serde_json::to_string_pretty(&scaled).context("failed encoding trim data")?;
+    json.push('\n');
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("failed creating {}", parent.display()))?;
+    }
+    fs::write(path, json).with_context(|| format!("failed writing {}", path.display()))
+}
+
+/// Frames grouped by `animations.pattern`/`animations.regex` (`hero/walk_01.png`,
+/// `hero/walk_02.png` -> `hero/walk`), ordered by frame number.
+struct Animation {
+    /// `hero/walk` for `hero/walk_NN.png`.
+    name: String,
+    /// Indices into the sprite list the animation was grouped from.
+    frames: Vec<usize>,
+    frame_ms: u32,
+    looped: bool,
+}
+
+impl Animation {
+    fn fps(&self) -> f32 {
+        1000.0 / self.frame_ms as f32
+    }
+}
+
+const DEFAULT_ANIMATION_PATTERN: &str = "{anim}_{frame}";
+const DEFAULT_FRAME_MS: u32 = 100;
+
+/// The regex sprite names (relative path without extension) are matched against; it
+/// has `anim` and `frame` capture groups.
+fn animation_regex(cfg: &Config) -> Result<Regex> {
+    let anims = cfg.animations.as_ref();
+    let pattern = anims.and_then(|a| a.pattern.as_deref());
+    let source = match (anims.and_then(|a| a.regex.as_deref()), pattern) {
+        (Some(_), Some(_)) => bail!("set either animations.pattern or animations.regex, not both"),
+        (Some(regex), None) => regex.to_string(),
+        (None, pattern) => {
+            let pattern = pattern.unwrap_or(DEFAULT_ANIMATION_PATTERN);
+            if !pattern.contains("{anim}") || !pattern.contains("{frame}") {
+                bail!("animations.pattern must contain {{anim}} and {{frame}}, got '{pattern}'");
+            }
+            let mut regex = String::from("^");
+            for (i, part) in pattern.split("{anim}").enumerate() {
+                if i > 0 {
+                    regex.push_str("(?P<anim>.+)");
+                }
+                let parts = part.split("{frame}").map(regex::escape);
+                regex.push_str(&parts.collect::<Vec<_>>().join("(?P<frame>[0-9]+)"));
+            }
+            regex.push('$');
+            regex
+        }
+    };
+    let regex =
+        Regex::new(&source).with_context(|| format!("invalid animation regex '{source}'"))?;
+    for group in ["anim", "frame"] {
+        if !regex.capture_names().any(|name| name == Some(group)) {
+            bail!("animation regex '{source}' needs a named '{group}' group");
+        }
+    }
+    Ok(regex)
+}
+
+fn animation_groups(cfg: &Config, sprites: &[Sprite]) -> Result<Vec<Animation>> {
+    let regex = animation_regex(cfg)?;
+    // Without an `[animations]` table the default pattern is only a guess, so names it
+    // cannot group cleanly are skipped with a warning instead of failing the command.
+    let strict = cfg.animations.is_some();
+    let mut groups: BTreeMap<String, Vec<(u32, usize)>> = BTreeMap::new();
+    let mut skipped = BTreeSet::new();
+    for (idx, sprite) in sprites.iter().enumerate() {
+        let name = normalize_for_glob(&sprite.path.with_extension(""));
+        let Some(caps) = regex.captures(&name) else {
+            continue;
+        };
+        // Optional or alternated groups may not take part in the match.
+        let (Some(anim), Some(frame)) = (caps.name("anim"), caps.name("frame")) else {
+            continue;
+        };
+        let (anim, frame) = (anim.as_str(), frame.as_str());
+        if anim.is_empty() || anim.ends_with('/') {
+            continue;
+        }
+        let frame = match frame.parse::<u32>() {
+            Ok(frame) => frame,
+            Err(_) if !strict => {
+                eprintln!(
+                    "warning: not grouping animation {anim}: {name} has frame '{frame}', which is not a number"
+                );
+                skipped.insert(anim.to_string());
+                continue;
+            }
+            Err(_) => bail!("{name}: frame '{frame}' is not a number"),
+        };
+        groups
+            .entry(anim.to_string())
+            .or_default()
+            .push((frame, idx));
+    }
+
+    let settings = cfg.animations.as_ref();
+    if let Some(settings) = settings {
+        for name in settings.overrides.keys() {
+            if !groups.contains_key(name) {
+                eprintln!("warning: animations.overrides.\"{name}\" matches no animation");
+            }
+        }
+    }
+
+    let mut animations = Vec::with_capacity(groups.len());
+    for (name, mut frames) in groups {
+        if skipped.contains(&name) {
+            continue;
+        }
+        frames.sort();
+        if let Some(pair) = frames.windows(2).find(|w| w[0].0 == w[1].0) {
+            let problem = format!(
+                "animation {name} has two frames numbered {} ({} and {})",
+                pair[0].0,
+                normalize_for_glob(&sprites[pair[0].1].path),
+                normalize_for_glob(&sprites[pair[1].1].path)
+            );
+            if strict {
+                bail!(problem);
+            }
+            eprintln!("warning: not grouping {problem}");
+            continue;
+        }
+        let over = settings.and_then(|s| s.overrides.get(&name));
+        let over_ms = over.and_then(|o| o.frame_ms);
+        // Aseprite timing beats the `[animations]` default; engines play one rate per
+        // animation, so the first frame's duration stands for all of them.
+        let durations = frames
+            .iter()
+            .filter_map(|(_, idx)| sprites[*idx].frame_ms)
+            .filter(|ms| *ms > 0)
+            .collect::<Vec<_>>();
+        if over_ms.is_none() && durations.windows(2).any(|w| w[0] != w[1]) {
+            eprintln!(
+                "warning: animation {name} has varying Aseprite frame durations; every frame plays for {} ms",
+                durations[0]
+            );
+        }
+        let frame_ms = over_ms
+            .or(durations.first().copied())
+            .or(settings.and_then(|s| s.frame_ms))
+            .unwrap_or(DEFAULT_FRAME_MS);
+        if frame_ms == 0 {
+            bail!("animation {name}: frame_ms must be > 0");
+        }
+        animations.push(Animation {
+            frames: frames.into_iter().map(|(_, idx)| idx).collect(),
+            frame_ms,
+            looped: over
+                .and_then(|o| o.looped)
+                .or(settings.and_then(|s| s.looped))
+                .unwrap_or(true),
+            name,
+        });
+    }
+    Ok(animations)
+}
+
+/// A decoded sprite plus what downstream stages need to know about it.
+#[derive(Clone)]
+struct Sprite {
+    /// Path relative to `paths.input`; Aseprite frames get virtual `.png` names.
+    path: PathBuf,
+    image: DynamicImage,
+    /// Set once the image has been cropped by `build.trim_transparent`.
+    trim: Option<TrimRect>,
+    /// Aseprite frame duration, used for animation timing unless overridden.
+    frame_ms: Option<u32>,
+    meta: SpriteMeta,
+}
+
+/// Optional per-source sidecar: `hero.png` (or `hero.aseprite`) reads `hero.toml`.
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SpriteMeta {
+    /// Normalized pivot (`[0.5, 1.0]` = bottom center). Defaults to the center.
+    pivot: Option<[f32; 2]>,
+    /// Custom properties passed through to engine exporters (Tiled, LDtk).
+    #[serde(default)]
+    properties: BTreeMap<String, PropertyValue>,
+    /// Free-form tags; exporters act on their own (`rpgmaker:character`, ...).
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(untagged)]
+enum PropertyValue {
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    String(String),
+}
+
+impl SpriteMeta {
+    fn pivot(&self) -> [f32; 2] {
+        self.pivot.unwrap_or([0.5, 0.5])
+    }
+}
+
+fn load_sprite_meta(cfg: &Config, file: &Path) -> Result<SpriteMeta> {
+    let path = cfg.paths.input.join(file).with_extension("toml");
+    let content = match fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(SpriteMeta::default()),
+        Err(err) => return Err(err).with_context(|| format!("failed reading {}", path.display())),
+    };
+    toml::from_str(&content).with_context(|| format!("failed parsing sidecar {}", path.display()))
+}
+
+fn load_sprites(cfg: &Config, jobs: usize) -> Result<Vec<Sprite>> {
+    let files = collect_input_pngs(cfg)?;
+    let layer_rules = aseprite_layer_rules(cfg)?;
+    let decoded = par_map(jobs, &files, |file| {
+        let abs = cfg.paths.input.join(file);
+        let bytes = fs::read(&abs).with_context(|| format!("failed reading {}", abs.display()))?;
+        let meta = load_sprite_meta(cfg, file)?;
+        let frames = decode_source(cfg, &layer_rules, file, &bytes)?;
+        Ok::<_, anyhow::Error>((frames, meta))
+    });
+
+    let mut sprites = Vec::with_capacity(files.len());
+    let mut origins = Vec::with_capacity(files.len());
+    for (file, result) in files.iter().zip(decoded) {
+        let (frames, meta) = result?;
+        for (path, image, frame_ms) in frames {
+            origins.push((normalize_for_glob(&path), normalize_for_glob(file)));
+            sprites.push(Sprite {
+                path,
+                image,
+                trim: None,
+                frame_ms,
+                meta: meta.clone(),
+            });
+        }
+    }
+    ensure_unique_sprites(origins.iter().map(|(s, f)| (s.as_str(), f.as_str())))?;
+    Ok(sprites)
+}
+
+fn is_aseprite_ext(ext: &str) -> bool {
+    ext.eq_ignore_ascii_case("ase") || ext.eq_ignore_ascii_case("aseprite")
+}
+
+/// Decodes one input file into sprites named relative to `paths.input`. PNGs map to
+/// themselves; Aseprite files expand into one sprite per frame (see `aseprite::Document::expand`)
+/// and carry that frame's duration.
+fn decode_source(
+    cfg: &Config,
+    layer_rules: &aseprite::LayerRules,
+    file: &Path,
+    bytes: &[u8],
+) -> Result<Vec<(PathBuf, DynamicImage, Option<u32>)>> {
+    let abs = cfg.paths.input.join(file);
+    let is_aseprite = file
+        .extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(is_aseprite_ext);
+    if !is_aseprite {
+        let img = image::load_from_memory(bytes)
+            .with_context(|| format!("failed reading image {}", abs.display()))?;
+        return Ok(vec![(file.to_path_buf(), img, None)]);
+    }
+
+    let doc = aseprite::Document::parse(bytes)
+        .with_context(|| format!("failed reading Aseprite file {}", abs.display()))?;
+    Ok(doc
+        .expand(file, layer_rules)
+        .into_iter()
+        .map(|frame| {
+            let image = DynamicImage::ImageRgba8(frame.image);
+            (frame.path, image, Some(frame.duration_ms))
+        })
+        .collect())
+}
+
+fn aseprite_layer_rules(cfg: &Config) -> Result<aseprite::LayerRules> {
+    let ase = cfg.inputs.aseprite.as_ref();
+    let include = ase
+        .and_then(|a| a.include_layers.as_deref())
+        .map(build_globset)
+        .transpose()?;
+    let exclude = build_globset(ase.map(|a| a.exclude_layers.as_slice()).unwrap_or_default())?;
+    Ok(aseprite::LayerRules { include, exclude })
+}
+
+/// Fails when two sources produce the same sprite path (e.g. `hero.png` next to a
+/// single-frame `hero.aseprite`), since one export would silently overwrite the other.
+fn ensure_unique_sprites<'a>(sprites: impl Iterator<Item = (&'a str, &'a str)>) -> Result<()> {
+    let mut seen = BTreeMap::new();
+    for (sprite, source) in sprites {
+        if let Some(first) = seen.insert(sprite, source) {
+            if first != source {
+                bail!("sprite path {sprite} is produced by both {first} and {source}");
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Maps `f` over `items` on up to `jobs` worker threads. Results come back in input
+/// order, so callers keep the deterministic ordering of `collect_input_pngs`.
+fn par_map<T, R, F>(jobs: usize, items: &[T], f: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync,
+{
+    let workers = jobs.clamp(1, items.len().max(1));
+    if workers == 1 {
+        return items.iter().map(f).collect();
+    }
+
+    let next = AtomicUsize::new(0);
+    let mut indexed = thread::scope(|scope| {
+        let handles = (0..workers)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut done = Vec::new();
+                    loop {
+                        let idx = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(item) = items.get(idx) else {
+                            break;
+                        };
+                        done.push((idx, f(item)));
+                    }
+                    done
+                })
+            })
+            .collect::<Vec<_>>();
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().expect("worker thread panicked"))
+            .collect::<Vec<_>>()
+    });
+    indexed.sort_by_key(|(idx, _)| *idx);
+    indexed.into_iter().map(|(_, result)| result).collect()
+}
+
+/// Sprites as they appear in exports (trimmed when `build.trim_transparent` is on),
+/// ordered by `sheet.sort`. Fully transparent sprites are skipped, as in `run_build`.
+fn load_atlas_sprites(cfg: &Config, jobs: usize) -> Result<Vec<Sprite>> {
+    Ok(prepare_atlas_sprites(cfg, load_sprites(cfg, jobs)?))
+}
+
+/// Sprites exactly as `welder build` exported them, for `welder export`: names and trims come
+/// from the build manifest and pixels from the smallest exported resolution, so engine
+/// resources match what ships. Palette variants follow as `<variant>/<sprite>`.
+fn load_export_sprites(cfg: &Config, jobs: usize) -> Result<Vec<Sprite>> {
+    let manifest = read_build_manifest(&cfg.paths.dist.join(BUILD_MANIFEST))?;
+    if manifest.sources.is_empty() {
+        bail!(
+            "no build manifest at {} (run 'welder build' first)",
+            cfg.paths.dist.join(BUILD_MANIFEST).display()
+        );
+    }
+    if manifest.config_hash != build_config_hash(cfg) {
+        bail!("exports are out of date with the config (run 'welder build' first)");
+    }
+    let mut resolutions = cfg.build.resolutions.clone();
+    resolutions.sort_unstable();
+    let variants = palette_variants(cfg)?;
+    let trees = std::iter::once(None).chain(variants.iter().map(|v| Some(v.name.as_str())));
+
+    let mut wanted = Vec::new();
+    for variant in trees {
+        for (source, entry) in &manifest.sources {
+            for (name, trim) in &entry.sprites {
+                wanted.push((variant, source, name, *trim));
+            }
+        }
+    }
+    let loaded = par_map(jobs, &wanted, |&(variant, source, name, trim)| {
+        let outputs = &manifest.sources[source].outputs;
+        let (factor, key) = resolutions
+            .iter()
+            .map(|&factor| (factor, export_key(variant, factor, name)))
+            .find(|(_, key)| outputs.contains_key(key))
+            .with_context(|| format!("{name} has no exports (run 'welder build' first)"))?;
+        let path = cfg.paths.exports.join(&key);
+        let bytes =
+            fs::read(&path).with_context(|| format!("failed reading {}", path.display()))?;
+        if sha256_hex(&bytes) != outputs[&key] {
+            bail!(
+                "{} changed since the last build (run 'welder build' first)",
+                path.display()
+            );
+        }
+        let image = image::load_from_memory(&bytes)
+            .with_context(|| format!("failed decoding {}", path.display()))?;
+        let meta = load_sprite_meta(cfg, Path::new(source))?;
+        Ok(Sprite {
+            path: PathBuf::from(variant.map_or_else(|| name.clone(), |v| format!("{v}/{name}"))),
+            image: unscale(&image.to_rgba8(), factor).into(),
+            trim,
+            frame_ms: manifest.sources[source].durations.get(name).copied(),
+            meta,
+        })
+    });
+    let mut sprites = loaded.into_iter().collect::<Result<Vec<_>>>()?;
+    sort_sprites(cfg, &mut sprites);
+    Ok(sprites)
+}
+
+/// Undoes the nearest-neighbour upscale of an export by sampling every `factor`th pixel.
+fn unscale(image: &RgbaImage, factor: u32) -> RgbaImage {
+    if factor == 1 {
+        return image.clone();
+    }
+    RgbaImage::from_fn(image.width() / factor, image.height() / factor, |x, y| {
+        *image.get_pixel(x * factor, y * factor)
+    })
+}
+
+fn prepare_atlas_sprites(cfg: &Config, loaded: Vec<Sprite>) -> Vec<Sprite> {
+    let trim = cfg.build.trim_transparent.unwrap_or(false);
+    let mut sprites = Vec::with_capacity(loaded.len());
+    for mut sprite in loaded {
+        if !trim {
+            sprites.push(sprite);
+            continue;
+        }
+        match trim_sprite(&sprite.image) {
+            Some((trimmed, rect)) => {
+                sprite.image = trimmed;
+                sprite.trim = Some(rect);
+                sprites.push(sprite);
+            }
+            None => eprintln!(
+                "warning: {} is fully transparent, skipping",
+                normalize_for_glob(&sprite.path)
+            ),
+        }
+    }
+    sort_sprites(cfg, &mut sprites);
+    sprites
+}
+
+/// Orders sprites by `sheet.sort`: `name` ascending, or `area`/`height`/`width`
+/// descending (largest first packs tighter), with the name as tie-breaker.
+fn sort_sprites(cfg: &Config, sprites: &mut [Sprite]) {
+    let sort = cfg
+        .sheet
+        .sort
+        .as_deref()
+        .unwrap_or("name")
+        .to_ascii_lowercase();
+    let key = |sprite: &Sprite| {
+        let img = &sprite.image;
+        match sort.as_str() {
+            "area" => img.width() as u64 * img.height() as u64,
+            "height" => img.height() as u64,
+            "width" => img.width() as u64,
+            _ => 0,
+        }
+    };
+    sprites.sort_by(|a, b| {
+        key(b)
+            .cmp(&key(a))
+            .then_with(|| a.path.as_os_str().cmp(b.path.as_os_str()))
+    });
+}
+
+fn atlas_format(cfg: &Config) -> Result<String> {
+    let format = cfg
+        .sheet
+        .format
+        .as_deref()
+        .unwrap_or("json-hash")
+        .to_ascii_lowercase();
+    if format != "json-hash" && format != "json-array" {
+        bail!("sheet.format must be 'json-hash' or 'json-array', got '{format}'");
+    }
+    Ok(format)
+}
+
+fn atlas_page_name(idx: usize) -> String {
+    format!("atlas-{:02}.png", idx + 1)
+}
+
+/// One packed atlas page and the sprites on it, in `sheet.sort` order.
+struct AtlasPage {
+    image: RgbaImage,
+    frames: Vec<AtlasFrame>,
+}
+
+/// A sprite's rect on its page, in output pixels.
+struct AtlasFrame {
+    name: String,
+    x: u32,
+    y: u32,
+    w: u32,
+    h: u32,
+    /// Trim relative to the untrimmed sprite, already scaled.
+    trim: Option<TrimRect>,
+    pivot: [f32; 2],
+}
+
+/// Packs `sprites` (already sorted) at `factor`x with MaxRects into as many pages as
+/// `sheet.max_width`/`max_height` require.
+fn build_atlas_pages(cfg: &Config, sprites: &[Sprite], factor: u32) -> Result<Vec<AtlasPage>> {
+    let limits = (cfg.sheet.max_width, cfg.sheet.max_height);
+    pack_atlas_pages(sprites, factor, cfg.sheet.padding_px, limits)
+}
+
+/// Packs sprites upscaled by `factor` onto pages of at most `max_width` x `max_height`.
+fn pack_atlas_pages(
+    sprites: &[Sprite],
+    factor: u32,
+    pad: u32,
+    (max_width, max_height): (u32, u32),
+) -> Result<Vec<AtlasPage>> {
+    let mut sizes = Vec::with_capacity(sprites.len());
+    for sprite in sprites {
+        let (w, h) = (
+            sprite.image.width() * factor,
+            sprite.image.height() * factor,
+        );
+        if w + pad * 2 > max_width || h + pad * 2 > max_height {
+            bail!(
+                "{} is {w}x{h} at {factor}x and does not fit in sheet.max_width/max_height ({max_width}x{max_height}) with {pad}px padding",
+                normalize_for_glob(&sprite.path)
+            );
+        }
+        sizes.push((w, h));
+    }
+    let (placements, page_sizes) = atlas::pack(&sizes, max_width, max_height, pad)
+        .with_context(|| format!("atlas packing failed at {factor}x"))?;
+
+    let mut pages = page_sizes
+        .iter()
+        .map(|size| AtlasPage {
+            image: RgbaImage::new(size.width, size.height),
+            frames: Vec::new(),
+        })
+        .collect::<Vec<_>>();
+    for (sprite, place) in sprites.iter().zip(placements) {
+        let img = &sprite.image;
+        let scaled = img.resize_exact(
+            img.width() * factor,
+            img.height() * factor,
+            FilterType::Nearest,
+        );
+        let page = &mut pages[place.page];
+        page.image
+            .copy_from(&scaled.to_rgba8(), place.x, place.y)
+            .context("failed placing sprite in atlas")?;
+        page.frames.push(AtlasFrame {
+            name: normalize_for_glob(&sprite.path),
+            x: place.x,
+            y: place.y,
+            w: scaled.width(),
+            h: scaled.height(),
+            trim: sprite.trim.map(|t| t.scaled(factor)),
+            pivot: sprite.meta.pivot(),
+        });
+    }
+    Ok(pages)
+}
+
+/// TexturePacker-compatible metadata for one page (`json-hash` or `json-array`).
+fn atlas_page_json(
+    format: &str,
+    page: &AtlasPage,
+    image_name: &str,
+    related: &[String],
+    animations: &[(String, Vec<String>)],
+    factor: u32,
+) -> serde_json::Value {
+    let frame_json = |frame: &AtlasFrame| {
+        let (trimmed, offset_x, offset_y, source_w, source_h) = match frame.trim {
+            Some(t) => (
+                (t.x, t.y, t.source_w, t.source_h) != (0, 0, t.w, t.h),
+                t.x,
+                t.y,
+                t.source_w,
+                t.source_h,
+            ),
+            None => (false, 0, 0, frame.w, frame.h),
+        };
+        json!({
+            "frame": { "x": frame.x, "y": frame.y, "w": frame.w, "h": frame.h },
+            "rotated": false,
+            "trimmed": trimmed,
+            "spriteSourceSize": { "x": offset_x, "y": offset_y, "w": frame.w, "h": frame.h },
+            "sourceSize": { "w": source_w, "h": source_h },
+            "pivot": { "x": frame.pivot[0], "y": frame.pivot[1] },
+        })
+    };
+
+    let frames = if format == "json-array" {
+        serde_json::Value::Array(
+            page.frames
+                .iter()
+                .map(|frame| {
+                    let mut value = json!({ "filename": frame.name });
+                    if let (Some(obj), serde_json::Value::Object(rest)) =
+                        (value.as_object_mut(), frame_json(frame))
+                    {
+                        obj.extend(rest);
+                    }
+                    value
+                })
+                .collect(),
+        )
+    } else {
+        serde_json::Value::Object(
+            page.frames
+                .iter()
+                .map(|frame| (frame.name.clone(), frame_json(frame)))
+                .collect(),
+        )
+    };
+
+    let mut meta = json!({
+        "app": env!("CARGO_PKG_REPOSITORY"),
+        "version": env!("CARGO_PKG_VERSION"),
+        "image": image_name,
+        "format": "RGBA8888",
+        "size": { "w": page.image.width(), "h": page.image.height() },
+        "scale": factor.to_string(),
+    });
+    if !related.is_empty() {
+        meta["related_multi_packs"] = json!(related);
+    }
+
+    let mut doc = json!({ "frames": frames });
+    // PixiJS-style `animations`: frame names per animation, limited to this page.
+    let on_page = |name: &String| page.frames.iter().any(|f| &f.name == name);
+    let page_animations = animations
+        .iter()
+        .filter_map(|(anim, frames)| {
+            let frames = frames.iter().filter(|f| on_page(f)).collect::<Vec<_>>();
+            (!frames.is_empty()).then(|| (anim.clone(), json!(frames)))
+        })
+        .collect::<serde_json::Map<_, _>>();
+    if !page_animations.is_empty() {
+        doc["animations"] = serde_json::Value::Object(page_animations);
+    }
+    doc["meta"] = meta;
+    doc
+}
+
+/// The preview sheet uses the same MaxRects layout as `welder atlas` at 1x, upscaled by
+/// `preview.scale`, so what the store page shows is what ships; it must fit on a single
+/// page.
+fn render_sheet(cfg: &Config, theme: &Theme, sprites: &[Sprite]) -> Result<RgbaImage> {
+    let scale = preview_scale(cfg);
+    let pad = preview_px(cfg, cfg.sheet.padding_px);
+    let limits = (
+        cfg.sheet.max_width.saturating_mul(scale),
+        cfg.sheet.max_height.saturating_mul(scale),
+    );
+    let mut pages = pack_atlas_pages(sprites, scale, pad, limits)?;
+    if pages.len() > 1 {
+        bail!(
+            "sheet overflow: sprites need {} pages of sheet.max_width/max_height ({}x{})",
+            pages.len(),
+            cfg.sheet.max_width,
+            cfg.sheet.max_height
+        );
+    }
+    let Some(page) = pages.pop() else {
+        let size = pad.saturating_mul(2).max(1);
+        return Ok(theme.background.canvas(size, size));
+    };
+
+    let mut canvas = theme
+        .background
+        .canvas(page.image.width(), page.image.height());
+    imageops::overlay(&mut canvas, &page.image, 0, 0);
+    Ok(canvas)
+}
+
+/// Square cells in rows, shared by the grid, cover and screenshot previews. Sprites are
+/// upscaled by `scale` and centered in their cell; sprites too large for a cell are shrunk
+/// to fit. With `label` set, each cell gets the sprite's file stem drawn below it.
+struct GridLayout<'a> {
+    scale: u32,
+    cell: u32,
+    pad: u32,
+    cols: u32,
+    label: Option<(&'a font::Font, u32, Rgba<u8>)>,
+}
+
+impl GridLayout<'_> {
+    /// Height of the label strip under each cell, including the gap above it.
+    fn label_height(&self) -> u32 {
+        self.label
+            .map_or(0, |(font, scale, _)| font.measure("", scale).1 + scale)
+    }
+
+    fn size(&self, count: usize) -> (u32, u32) {
+        let rows = (count as u32).div_ceil(self.cols);
+        let span = |n: u32, cell: u32| {
+            n.saturating_mul(cell)
+                .saturating_add((n + 1).saturating_mul(self.pad))
+                .max(1)
+        };
+        (
+            span(self.cols, self.cell),
+            span(rows, self.cell + self.label_height()),
+        )
+    }
+
+    /// Draws `sprites` with the grid's top-left corner at (`x`, `y`).
+    fn draw(&self, canvas: &mut RgbaImage, x: u32, y: u32, sprites: &[&Sprite]) {
+        let row_h = self.cell + self.label_height();
+        for (idx, sprite) in sprites.iter().enumerate() {
+            let i = idx as u32;
+            let col = i % self.cols;
+            let row = i / self.cols;
+            let x0 = x + self.pad + col.saturating_mul(self.cell + self.pad);
+            let y0 = y + self.pad + row.saturating_mul(row_h + self.pad);
+            let thumb = fit_in_cell(&sprite.image, self.scale, self.cell);
+            let ox = x0 + (self.cell - thumb.width()) / 2;
+            let oy = y0 + (self.cell - thumb.height()) / 2;
+            imageops::overlay(canvas, &thumb, ox as i64, oy as i64);
+
+            if let Some((font, scale, color)) = self.label {
+                let stem = sprite
+                    .path
+                    .file_stem()
+                    .map(|s| s.to_string_lossy())
+                    .unwrap_or_default();
+                // Long names are cut to the cell width rather than run into the neighbor.
+                let mut text = stem.to_string();
+                while !text.is_empty() && font.measure(&text, scale).0 > self.cell {
+                    text.pop();
+                }
+                let text_w = font.measure(&text, scale).0;
+                font.draw(
+                    canvas,
+                    &text,
+                    i64::from(x0 + (self.cell - text_w) / 2),
+                    i64::from(y0 + self.cell + scale),
+                    scale,
+                    color,
+                );
+            }
+        }
+    }
+}
+
+/// One horizontal band of a grid page: a section header or a row of cells.
+enum GridBand<'a> {
+    Header(String),
+    Row(Vec<&'a Sprite>),
+}
+
+/// Grid pages laid out as bands, ready to draw.
+struct GridPlan<'a> {
+    layout: GridLayout<'a>,
+    font: &'a font::Font,
+    header_scale: u32,
+    pages: Vec<Vec<GridBand<'a>>>,
+}
+
+impl GridPlan<'_> {
+    fn band_height(&self, band: &GridBand) -> u32 {
+        match band {
+            GridBand::Header(_) => self.font.measure("", self.header_scale).1 + self.layout.pad,
+            GridBand::Row(_) => self.layout.size(1).1 - self.layout.pad,
+        }
+    }
+
+    fn render(&self, theme: &Theme) -> Vec<RgbaImage> {
+        let width = self.layout.size(1).0;
+        let mut images = Vec::with_capacity(self.pages.len());
+        for bands in &self.pages {
+            let height = self.layout.pad + bands.iter().map(|b| self.band_height(b)).sum::<u32>();
+            let mut canvas = theme.background.canvas(width, height.max(1));
+            let mut y = 0;
+            for band in bands {
+                match band {
+                    GridBand::Header(title) => self.font.draw(
+                        &mut canvas,
+                        title,
+                        i64::from(self.layout.pad),
+                        i64::from(y + self.layout.pad),
+                        self.header_scale,
+                        theme.text,
+                    ),
+                    GridBand::Row(row) => self.layout.draw(&mut canvas, 0, y, row),
+                }
+                y += self.band_height(band);
+            }
+            images.push(canvas);
+        }
+        images
+    }
+}
+
+/// Sprites at `preview.scale` in `grid.columns` columns of `grid.cell_px` cells, optionally
+/// labeled and grouped by top-level folder, split into pages of at most
+/// `grid.page_height_px`.
+fn plan_grid<'a>(
+    cfg: &Config,
+    theme: &Theme,
+    font: &'a font::Font,
+    sprites: &'a [Sprite],
+) -> GridPlan<'a> {
+    let label_scale = preview_px(cfg, cfg.grid.label_scale.unwrap_or(1)).max(1);
+    let layout = GridLayout {
+        scale: preview_scale(cfg),
+        cell: preview_px(cfg, cfg.grid.cell_px).max(1),
+        pad: preview_px(cfg, cfg.grid.padding_px),
+        cols: cfg.grid.columns.max(1),
+        label: (cfg.grid.labels == Some(true)).then(|| {
+            // Labels sit a step below titles so headers stand out.
+            let mut color = theme.text;
+            color[3] = (u32::from(color[3]) * 4 / 5) as u8;
+            (font, label_scale, color)
+        }),
+    };
+    let page_limit = cfg.grid.page_height_px.unwrap_or(4096).max(1);
+
+    // Sprites at the input root come first, without a header.
+    let mut sections: BTreeMap<Option<String>, Vec<&Sprite>> = BTreeMap::new();
+    for sprite in sprites {
+        let key = if cfg.grid.sections == Some(true) {
+            let name = normalize_for_glob(&sprite.path);
+            name.split_once('/').map(|(dir, _)| section_title(dir))
+        } else {
+            None
+        };
+        sections.entry(key).or_default().push(sprite);
+    }
+
+    let mut plan = GridPlan {
+        layout,
+        font,
+        header_scale: label_scale * 2,
+        pages: vec![Vec::new()],
+    };
+    // Each page starts with the top padding; a section continuing onto a new page repeats
+    // its header there.
+    let pad = plan.layout.pad;
+    let mut used = pad;
+    for (title, members) in sections {
+        let header = title.clone().map(GridBand::Header);
+        if let Some(band) = &header {
+            let h = plan.band_height(band) + plan.band_height(&GridBand::Row(Vec::new()));
+            if used + h > page_limit && !plan.pages.last().is_some_and(Vec::is_empty) {
+                plan.pages.push(Vec::new());
+                used = pad;
+            }
+        }
+        if let Some(band) = header {
+            used += plan.band_height(&band);
+            plan.pages.last_mut().unwrap().push(band);
+        }
+        for chunk in members.chunks(plan.layout.cols as usize) {
+            let band = GridBand::Row(chunk.to_vec());
+            let h = plan.band_height(&band);
+            if used + h > page_limit
+                && plan
+                    .pages
+                    .last()
+                    .is_some_and(|p| p.iter().any(|b| matches!(b, GridBand::Row(_))))
+            {
+                plan.pages.push(Vec::new());
+                used = pad;
+                if let Some(title) = &title {
+                    let band = GridBand::Header(title.clone());
+                    used += plan.band_height(&band);
+                    plan.pages.last_mut().unwrap().push(band);
+                }
+            }
+            used += h;
+            plan.pages.last_mut().unwrap().push(band);
+        }
+    }
+    plan
+}
+
+/// File names of the grid pages for `theme`: `grid.png`, or `grid-NN.png` when paged.
+fn grid_page_names(theme: &Theme, pages: usize) -> Vec<String> {
+    if pages == 1 {
+        return vec![theme.file("grid", "png")];
+    }
+    (1..=pages)
+        .map(|n| theme.file(&format!("grid-{n:02}"), "png"))
+        .collect()
+}
+
+/// `props_small` -> `Props small`, for grid section headers.
+fn section_title(dir: &str) -> String {
+    let words = dir.replace(['_', '-'], " ");
+    let mut chars = words.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => words,
+    }
+}
+
+/// Whether `name` is a grid preview file for the theme with `suffix`: `grid.png` or a
+/// `grid-NN.png` page (`grid-light.png`, `grid-NN-light.png` for the `light` theme).
+fn is_grid_preview(name: &str, suffix: &str) -> bool {
+    name.strip_prefix("grid")
+        .and_then(|rest| rest.strip_suffix(".png"))
+        .and_then(|rest| rest.strip_suffix(suffix))
+        .is_some_and(|page| {
+            page.is_empty()
+                || page
+                    .strip_prefix('-')
+                    .is_some_and(|n| !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()))
+        })
+}
+
+/// A fixed-size store image: an optional title centered at the top and the sprites in
+/// the largest whole-pixel grid that fits below it.
+fn render_showcase(
+    cfg: &Config,
+    theme: &Theme,
+    font: &font::Font,
+    sprites: &[&Sprite],
+    (width, height): (u32, u32),
+    title: Option<(&str, u32)>,
+) -> Result<RgbaImage> {
+    let mut canvas = theme.background.canvas(width, height);
+    let margin = (width.min(height) / 20).max(1);
+    let mut top = margin;
+
+    if let Some((text, mut scale)) = title {
+        // Long names step down in size rather than run off the canvas.
+        while scale > 1 && font.measure(text, scale).0 + 2 * margin > width {
+            scale -= 1;
+        }
+        let (text_w, text_h) = font.measure(text, scale);
+        let x = width.saturating_sub(text_w) / 2;
+        font.draw(&mut canvas, text, x as i64, top as i64, scale, theme.text);
+        top += text_h + margin;
+    }
+
+    let area_w = width.saturating_sub(2 * margin);
+    let area_h = height.saturating_sub(top + margin);
+    let base = sprites
+        .iter()
+        .map(|s| s.image.width().max(s.image.height()))
+        .max()
+        .unwrap_or(1)
+        .max(1);
+    let count = sprites.len();
+    let mut best = None;
+    for scale in (1..=(area_w.max(area_h) / base).max(1)).rev() {
+        // Of the column counts that fit, take the one closest to the area's shape.
+        let fits = (1..=count.max(1) as u32)
+            .map(|cols| GridLayout {
+                scale,
+                cell: base * scale,
+                pad: cfg.grid.padding_px.saturating_mul(scale),
+                cols,
+                label: None,
+            })
+            .filter(|layout| {
+                let (w, h) = layout.size(count);
+                w <= area_w && h <= area_h
+            })
+            .min_by_key(|layout| {
+                let (w, h) = layout.size(count);
+                (area_w - w).abs_diff(area_h - h)
+            });
+        if fits.is_some() {
+            best = fits;
+            break;
+        }
+    }
+    let layout = best.with_context(|| {
+        format!("{count} sprite(s) do not fit in a {width}x{height} image at 1x")
+    })?;
+    let (grid_w, grid_h) = layout.size(count);
+    layout.draw(
+        &mut canvas,
+        margin + (area_w - grid_w) / 2,
+        top + (area_h - grid_h) / 2,
+        sprites,
+    );
+    Ok(canvas)
+}
+
+/// Sprites whose path matches one of `patterns`, or all of them when there are none.
+fn select_sprites<'a>(sprites: &'a [Sprite], patterns: &[String]) -> Result<Vec<&'a Sprite>> {
+    if patterns.is_empty() {
+        return Ok(sprites.iter().collect());
+    }
+    let globs = build_globset(patterns)?;
+    let selected = sprites
+        .iter()
+        .filter(|s| globs.is_match(normalize_for_glob(&s.path)))
+        .collect::<Vec<_>>();
+    if selected.is_empty() {
+        bail!("no sprites match {}", patterns.join(", "));
+    }
+    Ok(selected)
+}
+
+fn screenshot_sizes(cfg: &Config) -> Result<Vec<(u32, u32)>> {
+    let default = ["1280x720".to_string(), "1920x1080".to_string()];
+    let sizes = cfg
+        .preview
+        .screenshots
+        .as_ref()
+        .and_then(|s| s.sizes.as_deref())
+        .unwrap_or(&default);
+    if sizes.is_empty() {
+        bail!("preview.screenshots.sizes must not be empty");
+    }
+    sizes
+        .iter()
+        .map(|size| {
+            size.split_once('x')
+                .and_then(|(w, h)| Some((w.trim().parse().ok()?, h.trim().parse().ok()?)))
+                .filter(|&(w, h): &(u32, u32)| w > 0 && h > 0)
+                .with_context(|| {
+                    format!("preview.screenshots.sizes: expected WIDTHxHEIGHT, got '{size}'")
+                })
+        })
+        .collect()
+}
+
+/// `preview.scene.maps` with the file stems their previews are named after.
+fn scene_maps(cfg: &Config) -> Result<Vec<(&str, &Path)>> {
+    let maps = cfg.preview.scene.as_ref().map_or(&[][..], |s| &s.maps);
+    if maps.is_empty() {
+        bail!("the scene style needs preview.scene.maps");
+    }
+    let mut seen = BTreeMap::new();
+    for map in maps {
+        let stem = map
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .with_context(|| format!("preview.scene.maps: bad file name {}", map.display()))?;
+        if let Some(other) = seen.insert(stem, map.as_path()) {
+            bail!(
+                "preview.scene.maps: {} and {} would both write scenes/{stem}.png",
+                other.display(),
+                map.display()
+            );
+        }
+    }
+    Ok(maps
+        .iter()
+        .filter_map(|map| Some((map.file_stem()?.to_str()?, map.as_path())))
+        .collect())
+}
+
+/// Validated `[preview.anim]`: whether to render one showcase instead of a file per
+/// animation, and the formats to write.
+fn anim_preview_settings(cfg: &Config) -> Result<(bool, Vec<&'static str>)> {
+    let settings = cfg.preview.anim.as_ref();
+    let showcase = match settings.and_then(|a| a.mode.as_deref()) {
+        None => false,
+        Some(mode) if mode.eq_ignore_ascii_case("each") => false,
+        Some(mode) if mode.eq_ignore_ascii_case("showcase") => true,
+        Some(mode) => bail!("preview.anim.mode must be each or showcase (got '{mode}')"),
+    };
+    let mut formats = Vec::new();
+    for format in settings
+        .and_then(|a| a.formats.as_deref())
+        .unwrap_or(&["gif".to_string()])
+    {
+        let format = match format.to_ascii_lowercase().as_str() {
+            "gif" => "gif",
+            "apng" => "apng",
+            _ => bail!("preview.anim.formats entries must be gif or apng (got '{format}')"),
+        };
+        if !formats.contains(&format) {
+            formats.push(format);
+        }
+    }
+    if formats.is_empty() {
+        bail!("preview.anim.formats must not be empty");
+    }
+    Ok((showcase, formats))
+}
+
+/// Writes `anim/<name>.gif` per animation, or a single `anim.gif` showcase; APNGs use
+/// the `.png` extension so browsers and stores pick them up.
+fn write_anim_previews(
+    cfg: &Config,
+    theme: &Theme,
+    mark: Option<&RgbaImage>,
+    sprites: &[Sprite],
+    dry_run: bool,
+) -> Result<()> {
+    let (showcase, formats) = anim_preview_settings(cfg)?;
+    let animations = animation_groups(cfg, sprites)?;
+    if animations.is_empty() {
+        eprintln!("warning: preview: no animations found for the anim style");
+        return Ok(());
+    }
+
+    let dir = cfg.paths.previews.join(format!("anim{}", theme.suffix));
+    let outputs = if showcase {
+        vec![(dir, animations.iter().collect())]
+    } else {
+        if !dry_run {
+            if dir.exists() {
+                fs::remove_dir_all(&dir)
+                    .with_context(|| format!("failed removing {}", dir.display()))?;
+            }
+            fs::create_dir_all(&dir)
+                .with_context(|| format!("failed creating {}", dir.display()))?;
+        }
+        animations
+            .iter()
+            .map(|anim| (dir.join(anim.name.replace('/', "_")), vec![anim]))
+            .collect::<Vec<_>>()
+    };
+
+    for (stem, anims) in outputs {
+        let frames = if dry_run {
+            Vec::new()
+        } else {
+            render_anim_frames(cfg, theme, mark, sprites, &anims)?
+        };
+        let looped = anims.iter().any(|a| a.looped);
+        for &format in &formats {
+            let ext = if format == "gif" { "gif" } else { "png" };
+            let mut out = stem.clone().into_os_string();
+            out.push(format!(".{ext}"));
+            let out = PathBuf::from(out);
+            if dry_run {
+                println!("[dry-run] write {}", out.display());
+                continue;
+            }
+            let bytes = if format == "gif" {
+                anim::encode_gif(&frames, looped)?
+            } else {
+                anim::encode_apng(&frames, looped)?
+            };
+            fs::write(&out, bytes).with_context(|| format!("failed writing {}", out.display()))?;
+        }
+    }
+    Ok(())
+}
+
+/// Renders animations side by side in grid cells on a shared timeline as long as the
+/// longest one. Looping animations wrap around; the others hold their last frame.
+fn render_anim_frames(
+    cfg: &Config,
+    theme: &Theme,
+    mark: Option<&RgbaImage>,
+    sprites: &[Sprite],
+    anims: &[&Animation],
+) -> Result<Vec<anim::Frame>> {
+    let scale = preview_scale(cfg);
+    let pad = preview_px(cfg, cfg.grid.padding_px);
+    let images = anims
+        .iter()
+        .map(|anim| {
+            anim.frames
+                .iter()
+                .map(|&idx| {
+                    let image = &sprites[idx].image;
+                    image
+                        .resize_exact(
+                            image.width() * scale,
+                            image.height() * scale,
+                            FilterType::Nearest,
+                        )
+                        .to_rgba8()
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    let cell_w = images
+        .iter()
+        .flatten()
+        .map(|i| i.width())
+        .max()
+        .unwrap_or(1);
+    let cell_h = images
+        .iter()
+        .flatten()
+        .map(|i| i.height())
+        .max()
+        .unwrap_or(1);
+    let cols = cfg.grid.columns.clamp(1, anims.len().max(1) as u32);
+    let rows = (anims.len() as u32).div_ceil(cols);
+    let width = cols * cell_w + (cols + 1) * pad;
+    let height = rows * cell_h + (rows + 1) * pad;
+
+    // Every moment at which some animation changes frame.
+    let total = anims
+        .iter()
+        .map(|a| a.frame_ms * a.frames.len() as u32)
+        .max()
+        .unwrap_or(0);
+    let mut times = BTreeSet::new();
+    for anim in anims {
+        let mut k = 0;
+        while k * anim.frame_ms < total && (anim.looped || (k as usize) < anim.frames.len()) {
+            times.insert(k * anim.frame_ms);
+            k += 1;
+        }
+    }
+    let times = times.into_iter().collect::<Vec<_>>();
+
+    let mut frames: Vec<anim::Frame> = Vec::with_capacity(times.len());
+    for (i, &t) in times.iter().enumerate() {
+        let mut canvas = theme.background.canvas(width, height);
+        for (n, (anim, frames)) in anims.iter().zip(&images).enumerate() {
+            let k = (t / anim.frame_ms) as usize;
+            let k = if anim.looped {
+                k % frames.len()
+            } else {
+                k.min(frames.len() - 1)
+            };
+            let image = &frames[k];
+            let col = n as u32 % cols;
+            let row = n as u32 / cols;
+            let x = pad + col * (cell_w + pad) + (cell_w - image.width()) / 2;
+            let y = pad + row * (cell_h + pad) + (cell_h - image.height()) / 2;
+            imageops::overlay(&mut canvas, image, x as i64, y as i64);
+        }
+        apply_watermark(cfg, mark, &mut canvas);
+
+        let delay_ms = times.get(i + 1).copied().unwrap_or(total) - t;
+        match frames.last_mut() {
+            Some(prev) if prev.image == canvas => prev.delay_ms += delay_ms,
+            _ => frames.push(anim::Frame {
+                image: canvas,
+                delay_ms,
+            }),
+        }
+    }
+    Ok(frames)
+}
+
+fn fit_in_cell(img: &DynamicImage, scale: u32, cell_px: u32) -> RgbaImage {
+    let w = img.width().max(1);
+    let h = img.height().max(1);
+    let scale = (scale as f32)
+        .min(cell_px as f32 / w as f32)
+        .min(cell_px as f32 / h as f32);
+    let new_w = ((w as f32 * scale).floor() as u32).clamp(1, cell_px);
+    let new_h = ((h as f32 * scale).floor() as u32).clamp(1, cell_px);
+    img.resize_exact(new_w, new_h, FilterType::Nearest)
+        .to_rgba8()
+}
+
+fn preview_scale(cfg: &Config) -> u32 {
+    cfg.preview.scale.unwrap_or(1).max(1)
+}
+
+/// Converts a configured preview size to output pixels according to `preview.units`.
+fn preview_px(cfg: &Config, px: u32) -> u32 {
+    match cfg.preview.units.as_deref() {
+        Some(units) if units.eq_ignore_ascii_case("output") => px,
+        _ => px.saturating_mul(preview_scale(cfg)),
+    }
+}
+
+/// A resolved `preview.background`: what sits behind the sprites in every preview.
+enum Background {
+    Solid(Rgba<u8>),
+    Checker {
+        cell: u32,
+        colors: [Rgba<u8>; 2],
+    },
+    Gradient {
+        vertical: bool,
+        stops: Vec<Rgba<u8>>,
+    },
+    Tile(RgbaImage),
+}
+
+impl Background {
+    fn parse(cfg: &Config, bg: &BackgroundConfig) -> Result<Self> {
+        let pattern = match bg {
+            BackgroundConfig::Color(value) if value.trim().eq_ignore_ascii_case("checker") => {
+                return Ok(Background::Checker {
+                    cell: preview_px(cfg, 8).max(1),
+                    colors: [Rgba([255, 255, 255, 255]), Rgba([204, 204, 204, 255])],
+                });
+            }
+            BackgroundConfig::Color(value) => {
+                return Ok(Background::Solid(parse_hex_color(value)?))
+            }
+            BackgroundConfig::Pattern(pattern) => pattern,
+        };
+        let colors = pattern
+            .colors
+            .iter()
+            .map(|c| parse_hex_color(c))
+            .collect::<Result<Vec<_>>>()?;
+        let kinds = [
+            pattern.checker.is_some(),
+            pattern.gradient.is_some(),
+            pattern.image.is_some(),
+        ];
+        if kinds.iter().filter(|&&k| k).count() != 1 {
+            bail!("set exactly one of checker, gradient or image");
+        }
+
+        if let Some(cell) = pattern.checker {
+            if cell == 0 {
+                bail!("checker cell size must be > 0");
+            }
+            let colors = match colors.as_slice() {
+                [] => [Rgba([255, 255, 255, 255]), Rgba([204, 204, 204, 255])],
+                &[a, b] => [a, b],
+                _ => bail!("checker takes two colors"),
+            };
+            return Ok(Background::Checker {
+                cell: preview_px(cfg, cell).max(1),
+                colors,
+            });
+        }
+        if let Some(direction) = &pattern.gradient {
+            let vertical = match direction.to_ascii_lowercase().as_str() {
+                "vertical" => true,
+                "horizontal" => false,
+                _ => bail!("gradient must be vertical or horizontal (got '{direction}')"),
+            };
+            if colors.len() < 2 {
+                bail!("gradient needs at least two colors");
+            }
+            return Ok(Background::Gradient {
+                vertical,
+                stops: colors,
+            });
+        }
+        let path = pattern.image.as_ref().expect("one kind is set");
+        let tile = image::open(path)
+            .with_context(|| format!("failed reading {}", path.display()))?
+            .to_rgba8();
+        let factor = preview_px(cfg, 1).max(1);
+        let tile = imageops::resize(
+            &tile,
+            tile.width().saturating_mul(factor).max(1),
+            tile.height().saturating_mul(factor).max(1),
+            FilterType::Nearest,
+        );
+        Ok(Background::Tile(tile))
+    }
+
+    /// A `width` x `height` canvas filled with the background.
+    fn canvas(&self, width: u32, height: u32) -> RgbaImage {
+        match self {
+            Background::Solid(color) => RgbaImage::from_pixel(width, height, *color),
+            Background::Checker { cell, colors } => RgbaImage::from_fn(width, height, |x, y| {
+                colors[((x / cell + y / cell) % 2) as usize]
+            }),
+            Background::Gradient { vertical, stops } => {
+                let len = if *vertical { height } else { width };
+                let line = (0..len)
+                    .map(|i| gradient_at(stops, i as f32 / len.saturating_sub(1).max(1) as f32))
+                    .collect::<Vec<_>>();
+                RgbaImage::from_fn(width, height, |x, y| {
+                    line[if *vertical { y } else { x } as usize]
+                })
+            }
+            Background::Tile(tile) => RgbaImage::from_fn(width, height, |x, y| {
+                *tile.get_pixel(x % tile.width(), y % tile.height())
+            }),
+        }
+    }
+}
+
+/// The color at `t` (0..=1) along evenly spaced gradient `stops`.
+fn gradient_at(stops: &[Rgba<u8>], t: f32) -> Rgba<u8> {
+    let pos = t.clamp(0.0, 1.0) * (stops.len() - 1) as f32;
+    let idx = (pos.floor() as usize).min(stops.len() - 2);
+    let frac = pos - idx as f32;
+    let (a, b) = (stops[idx], stops[idx + 1]);
+    Rgba(std::array::from_fn(|c| {
+        (f32::from(a[c]) + (f32::from(b[c]) - f32::from(a[c])) * frac).round() as u8
+    }))
+}
+
+/// One set of preview colors. The default theme writes plain file names; each
+/// `[preview.themes.<name>]` writes the same previews with a `-<name>` suffix.
+struct Theme {
+    suffix: String,
+    background: Background,
+    text: Rgba<u8>,
+}
+
+impl Theme {
+    /// `grid` -> `grid-light.png` for the `light` theme.
+    fn file(&self, stem: &str, ext: &str) -> String {
+        format!("{stem}{}.{ext}", self.suffix)
+    }
+}
+
+fn preview_themes(cfg: &Config) -> Result<Vec<Theme>> {
+    let text = match &cfg.preview.text_color {
+        Some(color) => parse_hex_color(color).context("invalid preview.text_color")?,
+        None => Rgba([255, 255, 255, 255]),
+    };
+    let mut themes = vec![Theme {
+        suffix: String::new(),
+        background: Background::parse(cfg, &cfg.preview.background)
+            .context("invalid preview.background")?,
+        text,
+    }];
+    for (name, theme) in &cfg.preview.themes {
+        if !name.starts_with(|c: char| c.is_ascii_alphabetic())
+            || !name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            bail!("preview.themes: '{name}' must start with a letter and use only [A-Za-z0-9_-]");
+        }
+        themes.push(Theme {
+            suffix: format!("-{name}"),
+            background: Background::parse(cfg, &theme.background)
+                .with_context(|| format!("invalid preview.themes.{name}.background"))?,
+            text: match &theme.text_color {
+                Some(color) => parse_hex_color(color)
+                    .with_context(|| format!("invalid preview.themes.{name}.text_color"))?,
+                None => text,
+            },
+        });
+    }
+    Ok(themes)
+}
+
+/// Accepts `#RRGGBB` or `#RRGGBBAA`.
+fn parse_hex_color(s: &str) -> Result<Rgba<u8>> {
+    let value = s.trim().trim_start_matches('#');
+    if value.len() != 6 && value.len() != 8 {
+        bail!("expected a #RRGGBB or #RRGGBBAA color, got '{s}'");
+    }
+    let r = u8::from_str_radix(&value[0..2], 16).with_context(|| format!("bad red in '{s}'"))?;
+    let g = u8::from_str_radix(&value[2..4], 16).with_context(|| format!("bad green in '{s}'"))?;
+    let b = u8::from_str_radix(&value[4..6], 16).with_context(|| format!("bad blue in '{s}'"))?;
+    let a = match value.get(6..8) {
+        Some(a) => u8::from_str_radix(a, 16).with_context(|| format!("bad alpha in '{s}'"))?,
+        None => 255,
+    };
+    Ok(Rgba([r, g, b, a]))
+}
+
+fn preview_font(cfg: &Config) -> Result<font::Font> {
+    match &cfg.preview.font {
+        Some(font) => font::Font::load(&font.path, font.glyphs.as_deref()),
+        None => Ok(font::Font::fallback()),
+    }
+}
+
+/// The watermark at output size, before opacity: the logo, or the text in `color` on
+/// transparent. `None` when watermarks are disabled.
+fn watermark_mark(cfg: &Config, font: &font::Font, color: Rgba<u8>) -> Result<Option<RgbaImage>> {
+    let wm = match cfg.preview.watermark.as_ref() {
+        Some(wm) if wm.enabled => wm,
+        _ => return Ok(None),
+    };
+
+    if let Some(path) = &wm.logo {
+        let logo = image::open(path)
+            .with_context(|| format!("failed reading watermark logo {}", path.display()))?
+            .to_rgba8();
+        let scale = preview_px(cfg, wm.logo_scale.unwrap_or(1)).max(1);
+        let (w, h) = (logo.width() * scale, logo.height() * scale);
+        return Ok(Some(imageops::resize(&logo, w, h, FilterType::Nearest)));
+    }
+
+    let text = wm
+        .text
+        .as_deref()
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .unwrap_or("iamkaf");
+    let scale = preview_px(cfg, wm.glyph_scale.unwrap_or(2)).max(1);
+    let (w, h) = font.measure(text, scale);
+    let mut mark = RgbaImage::new(w.max(1), h.max(1));
+    font.draw(&mut mark, text, 0, 0, scale, color);
+    Ok(Some(mark))
+}
+
+fn apply_watermark(cfg: &Config, mark: Option<&RgbaImage>, image: &mut RgbaImage) {
+    let (Some(wm), Some(mark)) = (cfg.preview.watermark.as_ref(), mark) else {
+        return;
+    };
+    let opacity = wm.opacity.unwrap_or(0.12).clamp(0.0, 1.0);
+    let stamp = |image: &mut RgbaImage, x: u32, y: u32, src: &Rgba<u8>| {
+        if src[3] > 0 {
+            let alpha = (src[3] as f32 * opacity).round() as u8;
+            blend_pixel(image, x, y, Rgba([src[0], src[1], src[2], alpha]));
+        }
+    };
+
+    if wm
+        .mode
+        .as_deref()
+        .is_some_and(|m| m.eq_ignore_ascii_case("tile"))
+    {
+        // Walk the preview in the rotated frame of a brick-laid grid of marks.
+        let (sin, cos) = wm.angle.unwrap_or(30.0).to_radians().sin_cos();
+        let spacing = preview_px(cfg, wm.spacing_px.unwrap_or(32)) as f32;
+        let cell_w = mark.width() as f32 + spacing;
+        let cell_h = mark.height() as f32 + spacing;
+        for y in 0..image.height() {
+            for x in 0..image.width() {
+                let (fx, fy) = (x as f32 + 0.5, y as f32 + 0.5);
+                let v = fx * sin + fy * cos;
+                let row = (v / cell_h).floor();
+                let u = fx * cos - fy * sin - row * cell_w / 2.0;
+                let mu = u.rem_euclid(cell_w) as u32;
+                let mv = v.rem_euclid(cell_h) as u32;
+                if mu < mark.width() && mv < mark.height() {
+                    stamp(image, x, y, mark.get_pixel(mu, mv));
+                }
+            }
+        }
+        return;
+    }
+
+    let margin = preview_px(cfg, wm.margin_px.unwrap_or(12));
+    let position = wm.position.as_deref().unwrap_or("bottom-right");
+    let (mark_w, mark_h) = mark.dimensions();
+    let (x0, y0) = match position.to_ascii_lowercase().as_str() {
+        "top-left" | "tl" => (margin, margin),
+        "top-right" | "tr" => (image.width().saturating_sub(mark_w + margin), margin),
+        "bottom-left" | "bl" => (margin, image.height().saturating_sub(mark_h + margin)),
+        "center" => (
+            (image.width().saturating_sub(mark_w)) / 2,
+            (image.height().saturating_sub(mark_h)) / 2,
+        ),
+        _ => (
+            image.width().saturating_sub(mark_w + margin),
+            image.height().saturating_sub(mark_h + margin),
+        ),
+    };
+    for (mx, my, src) in mark.enumerate_pixels() {
+        let (x, y) = (x0 + mx, y0 + my);
+        if x < image.width() && y < image.height() {
+            stamp(image, x, y, src);
+        }
+    }
+}
+
+fn blend_pixel(image: &mut RgbaImage, x: u32, y: u32, src: Rgba<u8>) {
+    let dst = image.get_pixel_mut(x, y);
     let sa = src[3] as f32 / 255.0;
     let da = dst[3] as f32 / 255.0;
     let out_a = sa + da * (1.0 - sa);
@@ -1029,134 +3892,6 @@ fn blend_pixel(image: &mut RgbaImage, x: u32, y: u32, src: Rgba<u8>) {
     ]);
 }
 
-fn glyph_pattern(ch: char) -> [&'static str; 7] {
-    match ch {
-        'A' => [
-            "01110", "10001", "10001", "11111", "10001", "10001", "10001",
-        ],
-        'B' => [
-            "11110", "10001", "10001", "11110", "10001", "10001", "11110",
-        ],
-        'C' => [
-            "01111", "10000", "10000", "10000", "10000", "10000", "01111",
-        ],
-        'D' => [
-            "11110", "10001", "10001", "10001", "10001", "10001", "11110",
-        ],
-        'E' => [
-            "11111", "10000", "10000", "11110", "10000", "10000", "11111",
-        ],
-        'F' => [
-            "11111", "10000", "10000", "11110", "10000", "10000", "10000",
-        ],
-        'G' => [
-            "01111", "10000", "10000", "10011", "10001", "10001", "01111",
-        ],
-        'H' => [
-            "10001", "10001", "10001", "11111", "10001", "10001", "10001",
-        ],
-        'I' => [
-            "11111", "00100", "00100", "00100", "00100", "00100", "11111",
-        ],
-        'J' => [
-            "11111", "00010", "00010", "00010", "00010", "10010", "01100",
-        ],
-        'K' => [
-            "10001", "10010", "10100", "11000", "10100", "10010", "10001",
-        ],
-        'L' => [
-            "10000", "10000", "10000", "10000", "10000", "10000", "11111",
-        ],
-        'M' => [
-            "10001", "11011", "10101", "10101", "10001", "10001", "10001",
-        ],
-        'N' => [
-            "10001", "10001", "11001", "10101", "10011", "10001", "10001",
-        ],
-        'O' => [
-            "01110", "10001", "10001", "10001", "10001", "10001", "01110",
-        ],
-        'P' => [
-            "11110", "10001", "10001", "11110", "10000", "10000", "10000",
-        ],
-        'Q' => [
-            "01110", "10001", "10001", "10001", "10101", "10010", "01101",
-        ],
-        'R' => [
-            "11110", "10001", "10001", "11110", "10100", "10010", "10001",
-        ],
-        'S' => [
-            "01111", "10000", "10000", "01110", "00001", "00001", "11110",
-        ],
-        'T' => [
-            "11111", "00100", "00100", "00100", "00100", "00100", "00100",
-        ],
-        'U' => [
-            "10001", "10001", "10001", "10001", "10001", "10001", "01110",
-        ],
-        'V' => [
-            "10001", "10001", "10001", "10001", "10001", "01010", "00100",
-        ],
-        'W' => [
-            "10001", "10001", "10001", "10101", "10101", "10101", "01010",
-        ],
-        'X' => [
-            "10001", "10001", "01010", "00100", "01010", "10001", "10001",
-        ],
-        'Y' => [
-            "10001", "10001", "01010", "00100", "00100", "00100", "00100",
-        ],
-        'Z' => [
-            "11111", "00001", "00010", "00100", "01000", "10000", "11111",
-        ],
-        '0' => [
-            "01110", "10001", "10011", "10101", "11001", "10001", "01110",
-        ],
-        '1' => [
-            "00100", "01100", "00100", "00100", "00100", "00100", "01110",
-        ],
-        '2' => [
-            "01110", "10001", "00001", "00010", "00100", "01000", "11111",
-        ],
-        '3' => [
-            "11110", "00001", "00001", "01110", "00001", "00001", "11110",
-        ],
-        '4' => [
-            "00010", "00110", "01010", "10010", "11111", "00010", "00010",
-        ],
-        '5' => [
-            "11111", "10000", "10000", "11110", "00001", "00001", "11110",
-        ],
-        '6' => [
-            "01110", "10000", "10000", "11110", "10001", "10001", "01110",
-        ],
-        '7' => [
-            "11111", "00001", "00010", "00100", "01000", "01000", "01000",
-        ],
-        '8' => [
-            "01110", "10001", "10001", "01110", "10001", "10001", "01110",
-        ],
-        '9' => [
-            "01110", "10001", "10001", "01111", "00001", "00001", "01110",
-        ],
-        '-' => [
-            "00000", "00000", "00000", "11111", "00000", "00000", "00000",
-        ],
-        '_' => [
-            "00000", "00000", "00000", "00000", "00000", "00000", "11111",
-        ],
-        '.' => [
-            "00000", "00000", "00000", "00000", "00000", "00110", "00110",
-        ],
-        ' ' => [
-            "00000", "00000", "00000", "00000", "00000", "00000", "00000",
-        ],
-        _ => [
-            "11111", "10001", "00110", "00100", "00110", "10001", "11111",
-        ],
-    }
-}
-
 fn generate_readme_if_configured(cfg: &Config) -> Result<Option<String>> {
     let Some(metadata) = cfg.metadata.as_ref() else {
         return Ok(None);
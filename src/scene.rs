@@ -0,0 +1,608 @@
+//! Scene previews: hand-made demo maps composited from the pack's sprites at 1x.
+//!
+//! Three sources are understood:
+//! - Tiled `.tmx` maps (orthogonal, finite). Tilesets may be inline or external `.tsx`,
+//!   either one spritesheet image or a collection of images; image paths are relative to
+//!   the map or tileset file. Tile layers (CSV, XML or base64 with optional zlib/gzip),
+//!   tile objects and image layers are drawn in document order with visibility, opacity,
+//!   offsets and tile flips. Object rotation is ignored.
+//! - `.toml` layouts: a one-character `legend` of sprite names and `layers` of text rows.
+//! - `.csv` layouts: one sprite name per cell, blank for nothing.
+//!
+//! Image paths that fall under `paths.input` resolve to the loaded sprites (so Aseprite
+//! frames work); anything else is read from disk. Like Tiled, tiles taller than the map
+//! grid are bottom-aligned to their cell.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use base64::Engine as _;
+use image::imageops::{self, FilterType};
+use image::RgbaImage;
+use serde::Deserialize;
+
+use crate::{normalize_for_glob, Sprite};
+
+const FLIP_H: u32 = 0x8000_0000;
+const FLIP_V: u32 = 0x4000_0000;
+const FLIP_D: u32 = 0x2000_0000;
+/// Flag bits Tiled may set on a gid, including the hexagonal rotation bit.
+const GID_FLAGS: u32 = 0xF000_0000;
+
+/// Resolves sprite references to images.
+struct Sprites<'a> {
+    input: PathBuf,
+    by_name: HashMap<String, &'a Sprite>,
+}
+
+impl<'a> Sprites<'a> {
+    fn new(input: &Path, sprites: &'a [Sprite]) -> Self {
+        Sprites {
+            input: lexical(input),
+            by_name: sprites
+                .iter()
+                .map(|s| (normalize_for_glob(&s.path), s))
+                .collect(),
+        }
+    }
+
+    /// A sprite by its path under `paths.input`; the `.png` may be left off.
+    fn named(&self, name: &str) -> Result<RgbaImage> {
+        let name = name.trim().trim_start_matches("./").replace('\\', "/");
+        self.by_name
+            .get(&name)
+            .or_else(|| self.by_name.get(&format!("{name}.png")))
+            .map(|s| s.image.to_rgba8())
+            .with_context(|| format!("no sprite named '{name}'"))
+    }
+
+    /// An image file: a loaded sprite when it lives under `paths.input`, else from disk.
+    fn file(&self, path: &Path) -> Result<RgbaImage> {
+        let path = lexical(path);
+        if let Ok(rel) = path.strip_prefix(&self.input) {
+            if let Ok(image) = self.named(&normalize_for_glob(rel)) {
+                return Ok(image);
+            }
+        }
+        Ok(image::open(&path)
+            .with_context(|| format!("failed reading {}", path.display()))?
+            .to_rgba8())
+    }
+}
+
+/// Absolute form of `path` with `.` and `..` folded away, without touching the disk.
+fn lexical(path: &Path) -> PathBuf {
+    let path = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    let mut out = PathBuf::new();
+    for part in path.components() {
+        match part {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                out.pop();
+            }
+            other => out.push(other),
+        }
+    }
+    out
+}
+
+/// Renders the map or layout at `path` at 1x on a transparent canvas.
+pub fn render(path: &Path, input: &Path, sprites: &[Sprite]) -> Result<RgbaImage> {
+    let sprites = Sprites::new(input, sprites);
+    let ext = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    let result = match ext.as_str() {
+        "tmx" => render_tmx(path, &sprites),
+        "toml" => render_toml(path, &sprites),
+        "csv" => render_csv(path, &sprites),
+        _ => bail!("expected a .tmx, .toml or .csv scene"),
+    };
+    result.with_context(|| format!("scene {}", path.display()))
+}
+
+/// Draws `image` with its top-left corner at (`x`, `y`), its alpha scaled by `opacity`.
+fn draw(canvas: &mut RgbaImage, image: &RgbaImage, x: i64, y: i64, opacity: f32) {
+    if opacity >= 1.0 {
+        imageops::overlay(canvas, image, x, y);
+        return;
+    }
+    let mut faded = image.clone();
+    for p in faded.pixels_mut() {
+        p[3] = (f32::from(p[3]) * opacity).round() as u8;
+    }
+    imageops::overlay(canvas, &faded, x, y);
+}
+
+struct Tileset {
+    first_gid: u32,
+    tiles: HashMap<u32, RgbaImage>,
+}
+
+fn render_tmx(path: &Path, sprites: &Sprites) -> Result<RgbaImage> {
+    let text =
+        fs::read_to_string(path).with_context(|| format!("failed reading {}", path.display()))?;
+    let doc = roxmltree::Document::parse(&text).context("invalid TMX")?;
+    let map = doc.root_element();
+    if !map.has_tag_name("map") {
+        bail!(
+            "root element is <{}>, expected <map>",
+            map.tag_name().name()
+        );
+    }
+    if let Some(orientation) = map.attribute("orientation") {
+        if orientation != "orthogonal" {
+            bail!("only orthogonal maps are supported (got {orientation})");
+        }
+    }
+    if map.attribute("infinite") == Some("1") {
+        bail!("infinite maps are not supported; resize the map to a fixed size in Tiled");
+    }
+    let width = attr_u32(map, "width")?;
+    let height = attr_u32(map, "height")?;
+    let tile_w = attr_u32(map, "tilewidth")?;
+    let tile_h = attr_u32(map, "tileheight")?;
+    let dir = path.parent().unwrap_or(Path::new(""));
+
+    let mut tilesets = Vec::new();
+    for node in map.children().filter(|n| n.has_tag_name("tileset")) {
+        let first_gid = attr_u32(node, "firstgid")?;
+        let tiles = match node.attribute("source") {
+            Some(source) => {
+                let tsx = dir.join(source);
+                let text = fs::read_to_string(&tsx)
+                    .with_context(|| format!("failed reading {}", tsx.display()))?;
+                let doc = roxmltree::Document::parse(&text)
+                    .with_context(|| format!("invalid tileset {}", tsx.display()))?;
+                load_tileset(
+                    doc.root_element(),
+                    tsx.parent().unwrap_or(Path::new("")),
+                    sprites,
+                )
+                .with_context(|| format!("tileset {}", tsx.display()))?
+            }
+            None => load_tileset(node, dir, sprites)?,
+        };
+        tilesets.push(Tileset { first_gid, tiles });
+    }
+    tilesets.sort_by_key(|t| t.first_gid);
+
+    let mut scene = TmxScene {
+        canvas: RgbaImage::new(width.saturating_mul(tile_w), height.saturating_mul(tile_h)),
+        tilesets,
+        flipped: HashMap::new(),
+        size: (width, height, tile_w, tile_h),
+        dir,
+        sprites,
+    };
+    scene.draw_layers(map, (0.0, 0.0), 1.0)?;
+    Ok(scene.canvas)
+}
+
+/// Tile images by local id: cut from a spritesheet, or one image per tile.
+fn load_tileset(
+    node: roxmltree::Node,
+    dir: &Path,
+    sprites: &Sprites,
+) -> Result<HashMap<u32, RgbaImage>> {
+    let mut tiles = HashMap::new();
+    if let Some(image) = node.children().find(|n| n.has_tag_name("image")) {
+        let sheet = sprites.file(&dir.join(attr(image, "source")?))?;
+        let tile_w = attr_u32(node, "tilewidth")?;
+        let tile_h = attr_u32(node, "tileheight")?;
+        let spacing = node
+            .attribute("spacing")
+            .map_or(Ok(0), |_| attr_u32(node, "spacing"))?;
+        let margin = node
+            .attribute("margin")
+            .map_or(Ok(0), |_| attr_u32(node, "margin"))?;
+        let columns = match node.attribute("columns") {
+            Some(_) => attr_u32(node, "columns")?,
+            None => (sheet.width().saturating_sub(margin) + spacing) / (tile_w + spacing).max(1),
+        };
+        let count = match node.attribute("tilecount") {
+            Some(_) => attr_u32(node, "tilecount")?,
+            None => {
+                let rows =
+                    (sheet.height().saturating_sub(margin) + spacing) / (tile_h + spacing).max(1);
+                columns * rows
+            }
+        };
+        for id in 0..count {
+            let x = margin + (id % columns.max(1)) * (tile_w + spacing);
+            let y = margin + (id / columns.max(1)) * (tile_h + spacing);
+            if x + tile_w > sheet.width() || y + tile_h > sheet.height() {
+                bail!(
+                    "tile {id} lies outside the {}x{} image",
+                    sheet.width(),
+                    sheet.height()
+                );
+            }
+            tiles.insert(
+                id,
+                imageops::crop_imm(&sheet, x, y, tile_w, tile_h).to_image(),
+            );
+        }
+    }
+    for tile in node.children().filter(|n| n.has_tag_name("tile")) {
+        if let Some(image) = tile.children().find(|n| n.has_tag_name("image")) {
+            let id = attr_u32(tile, "id")?;
+            tiles.insert(id, sprites.file(&dir.join(attr(image, "source")?))?);
+        }
+    }
+    Ok(tiles)
+}
+
+struct TmxScene<'a> {
+    canvas: RgbaImage,
+    tilesets: Vec<Tileset>,
+    /// Flipped variants of tiles, see `tile_image`.
+    flipped: HashMap<u32, RgbaImage>,
+    /// Map width and height in tiles, then the tile size.
+    size: (u32, u32, u32, u32),
+    dir: &'a Path,
+    sprites: &'a Sprites<'a>,
+}
+
+impl TmxScene<'_> {
+    /// Draws the layers under `parent` in order; groups add their offset and opacity.
+    fn draw_layers(
+        &mut self,
+        parent: roxmltree::Node,
+        offset: (f32, f32),
+        opacity: f32,
+    ) -> Result<()> {
+        for node in parent.children().filter(|n| n.is_element()) {
+            if !["layer", "objectgroup", "imagelayer", "group"].contains(&node.tag_name().name()) {
+                continue;
+            }
+            if node.attribute("visible") == Some("0") {
+                continue;
+            }
+            let offset = (
+                offset.0 + attr_f32(node, "offsetx")?.unwrap_or(0.0),
+                offset.1 + attr_f32(node, "offsety")?.unwrap_or(0.0),
+            );
+            let opacity = opacity * attr_f32(node, "opacity")?.unwrap_or(1.0);
+            let name = node.attribute("name").unwrap_or("");
+            let result = match node.tag_name().name() {
+                "layer" => self.draw_tile_layer(node, offset, opacity),
+                "objectgroup" => self.draw_objects(node, offset, opacity),
+                "imagelayer" => match node.children().find(|n| n.has_tag_name("image")) {
+                    Some(image) => {
+                        let image = self.sprites.file(&self.dir.join(attr(image, "source")?))?;
+                        draw(
+                            &mut self.canvas,
+                            &image,
+                            offset.0.round() as i64,
+                            offset.1.round() as i64,
+                            opacity,
+                        );
+                        Ok(())
+                    }
+                    None => Ok(()),
+                },
+                _ => self.draw_layers(node, offset, opacity),
+            };
+            result.with_context(|| format!("layer '{name}'"))?;
+        }
+        Ok(())
+    }
+
+    fn draw_tile_layer(
+        &mut self,
+        layer: roxmltree::Node,
+        offset: (f32, f32),
+        opacity: f32,
+    ) -> Result<()> {
+        let (width, height, tile_w, tile_h) = self.size;
+        let data = layer
+            .children()
+            .find(|n| n.has_tag_name("data"))
+            .context("missing <data>")?;
+        let gids = layer_gids(data)?;
+        if gids.len() != (width * height) as usize {
+            bail!("has {} tiles, expected {width}x{height}", gids.len());
+        }
+        for (idx, &gid) in gids.iter().enumerate() {
+            let Some(tile) = tile_image(&self.tilesets, &mut self.flipped, gid)? else {
+                continue;
+            };
+            let col = idx as u32 % width;
+            let row = idx as u32 / width;
+            let x = i64::from(col * tile_w) + offset.0.round() as i64;
+            let y =
+                i64::from((row + 1) * tile_h) - i64::from(tile.height()) + offset.1.round() as i64;
+            draw(&mut self.canvas, tile, x, y, opacity);
+        }
+        Ok(())
+    }
+
+    /// Tile objects, drawn from their bottom-left corner and stretched to their size.
+    fn draw_objects(
+        &mut self,
+        group: roxmltree::Node,
+        offset: (f32, f32),
+        opacity: f32,
+    ) -> Result<()> {
+        for object in group.children().filter(|n| n.has_tag_name("object")) {
+            if object.attribute("visible") == Some("0") {
+                continue;
+            }
+            let Some(gid) = object.attribute("gid") else {
+                continue;
+            };
+            let gid = gid
+                .parse::<u32>()
+                .with_context(|| format!("object has bad gid '{gid}'"))?;
+            let Some(tile) = tile_image(&self.tilesets, &mut self.flipped, gid)? else {
+                continue;
+            };
+            let w = attr_f32(object, "width")?.map_or(tile.width(), |w| w.round() as u32);
+            let h = attr_f32(object, "height")?.map_or(tile.height(), |h| h.round() as u32);
+            let stretched;
+            let tile = if (w, h) != tile.dimensions() && w > 0 && h > 0 {
+                stretched = imageops::resize(tile, w, h, FilterType::Nearest);
+                &stretched
+            } else {
+                tile
+            };
+            let x = attr_f32(object, "x")?.unwrap_or(0.0) + offset.0;
+            let y = attr_f32(object, "y")?.unwrap_or(0.0) + offset.1;
+            draw(
+                &mut self.canvas,
+                tile,
+                x.round() as i64,
+                y.round() as i64 - i64::from(tile.height()),
+                opacity,
+            );
+        }
+        Ok(())
+    }
+}
+
+/// The image for `gid` with its flips applied, or `None` for an empty cell. Flipped
+/// variants are cached in `flipped`, keyed by the gid including its flag bits.
+fn tile_image<'t>(
+    tilesets: &'t [Tileset],
+    flipped: &'t mut HashMap<u32, RgbaImage>,
+    gid: u32,
+) -> Result<Option<&'t RgbaImage>> {
+    let id = gid & !GID_FLAGS;
+    if id == 0 {
+        return Ok(None);
+    }
+    let Some(tileset) = tilesets.iter().rev().find(|t| t.first_gid <= id) else {
+        bail!("gid {id} is not in any tileset");
+    };
+    let image = tileset
+        .tiles
+        .get(&(id - tileset.first_gid))
+        .with_context(|| format!("gid {id} has no image"))?;
+    if gid & (FLIP_H | FLIP_V | FLIP_D) == 0 {
+        return Ok(Some(image));
+    }
+    Ok(Some(flipped.entry(gid).or_insert_with(|| {
+        // Tiled applies the diagonal flip first, then horizontal and vertical.
+        let mut out = image.clone();
+        if gid & FLIP_D != 0 {
+            out = imageops::flip_horizontal(&imageops::rotate90(&out));
+        }
+        if gid & FLIP_H != 0 {
+            out = imageops::flip_horizontal(&out);
+        }
+        if gid & FLIP_V != 0 {
+            out = imageops::flip_vertical(&out);
+        }
+        out
+    })))
+}
+
+fn layer_gids(data: roxmltree::Node) -> Result<Vec<u32>> {
+    if data.children().any(|n| n.has_tag_name("chunk")) {
+        bail!("chunked (infinite) layers are not supported");
+    }
+    let text = data.text().unwrap_or("");
+    match data.attribute("encoding") {
+        None => data
+            .children()
+            .filter(|n| n.has_tag_name("tile"))
+            .map(|tile| match tile.attribute("gid") {
+                Some(gid) => gid.parse().with_context(|| format!("bad gid '{gid}'")),
+                None => Ok(0),
+            })
+            .collect(),
+        Some("csv") => text
+            .split(',')
+            .map(str::trim)
+            .filter(|v| !v.is_empty())
+            .map(|v| v.parse().with_context(|| format!("bad gid '{v}'")))
+            .collect(),
+        Some("base64") => {
+            let compact = text
+                .chars()
+                .filter(|c| !c.is_whitespace())
+                .collect::<String>();
+            let raw = base64::engine::general_purpose::STANDARD
+                .decode(compact)
+                .context("invalid base64 layer data")?;
+            let bytes = match data.attribute("compression") {
+                None | Some("") => raw,
+                Some("zlib") => {
+                    let mut out = Vec::new();
+                    flate2::read::ZlibDecoder::new(raw.as_slice())
+                        .read_to_end(&mut out)
+                        .context("invalid zlib layer data")?;
+                    out
+                }
+                Some("gzip") => {
+                    let mut out = Vec::new();
+                    flate2::read::GzDecoder::new(raw.as_slice())
+                        .read_to_end(&mut out)
+                        .context("invalid gzip layer data")?;
+                    out
+                }
+                Some(other) => bail!("unsupported layer compression '{other}'"),
+            };
+            if !bytes.len().is_multiple_of(4) {
+                bail!("layer data is not a whole number of gids");
+            }
+            Ok(bytes
+                .chunks_exact(4)
+                .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+                .collect())
+        }
+        Some(other) => bail!("unsupported layer encoding '{other}'"),
+    }
+}
+
+fn attr<'a>(node: roxmltree::Node<'a, '_>, name: &str) -> Result<&'a str> {
+    node.attribute(name)
+        .with_context(|| format!("<{}> is missing {name}", node.tag_name().name()))
+}
+
+fn attr_u32(node: roxmltree::Node, name: &str) -> Result<u32> {
+    let value = attr(node, name)?;
+    value
+        .trim()
+        .parse()
+        .with_context(|| format!("<{}> {name}: bad number '{value}'", node.tag_name().name()))
+}
+
+fn attr_f32(node: roxmltree::Node, name: &str) -> Result<Option<f32>> {
+    node.attribute(name)
+        .map(|value| {
+            value.trim().parse().with_context(|| {
+                format!("<{}> {name}: bad number '{value}'", node.tag_name().name())
+            })
+        })
+        .transpose()
+}
+
+/// A `.toml` scene: rows of characters looked up in `legend`.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Layout {
+    /// Grid cell size; defaults to the largest sprite used.
+    tile_width: Option<u32>,
+    tile_height: Option<u32>,
+    /// One character to a sprite name (path under `paths.input`, `.png` optional).
+    legend: BTreeMap<String, String>,
+    /// Bottom to top; spaces are empty cells.
+    layers: Vec<LayoutLayer>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LayoutLayer {
+    rows: String,
+}
+
+fn render_toml(path: &Path, sprites: &Sprites) -> Result<RgbaImage> {
+    let text =
+        fs::read_to_string(path).with_context(|| format!("failed reading {}", path.display()))?;
+    let layout: Layout = toml::from_str(&text).context("invalid layout")?;
+    let mut legend = HashMap::new();
+    for (key, name) in &layout.legend {
+        let mut chars = key.chars();
+        let (Some(ch), None) = (chars.next(), chars.next()) else {
+            bail!("legend key '{key}' must be a single character");
+        };
+        if ch == ' ' {
+            bail!("legend key ' ' is reserved for empty cells");
+        }
+        legend.insert(
+            ch,
+            sprites
+                .named(name)
+                .with_context(|| format!("legend '{key}'"))?,
+        );
+    }
+    let mut layers = Vec::with_capacity(layout.layers.len());
+    for (n, layer) in layout.layers.iter().enumerate() {
+        let mut rows = Vec::new();
+        for line in layer.rows.lines() {
+            let mut row = Vec::new();
+            for ch in line.chars() {
+                if ch == ' ' {
+                    row.push(None);
+                } else {
+                    let image = legend
+                        .get(&ch)
+                        .with_context(|| format!("layer {}: '{ch}' is not in the legend", n + 1))?;
+                    row.push(Some(image));
+                }
+            }
+            rows.push(row);
+        }
+        layers.push(rows);
+    }
+    Ok(compose_cells(
+        &layers,
+        layout.tile_width,
+        layout.tile_height,
+    ))
+}
+
+fn render_csv(path: &Path, sprites: &Sprites) -> Result<RgbaImage> {
+    let text =
+        fs::read_to_string(path).with_context(|| format!("failed reading {}", path.display()))?;
+    let mut images: HashMap<&str, RgbaImage> = HashMap::new();
+    for (n, line) in text.lines().enumerate() {
+        for name in line.split(',').map(str::trim).filter(|c| !c.is_empty()) {
+            if !images.contains_key(name) {
+                let image = sprites
+                    .named(name)
+                    .with_context(|| format!("line {}", n + 1))?;
+                images.insert(name, image);
+            }
+        }
+    }
+    let rows = text
+        .lines()
+        .map(|line| {
+            line.split(',')
+                .map(str::trim)
+                .map(|name| images.get(name))
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    Ok(compose_cells(&[rows], None, None))
+}
+
+/// Stacks layers of cell rows; cells default to the largest image used.
+fn compose_cells(
+    layers: &[Vec<Vec<Option<&RgbaImage>>>],
+    tile_w: Option<u32>,
+    tile_h: Option<u32>,
+) -> RgbaImage {
+    let used = || layers.iter().flatten().flatten().flatten();
+    let tile_w = tile_w.unwrap_or_else(|| used().map(|i| i.width()).max().unwrap_or(1));
+    let tile_h = tile_h.unwrap_or_else(|| used().map(|i| i.height()).max().unwrap_or(1));
+    let cols = layers.iter().flatten().map(Vec::len).max().unwrap_or(0) as u32;
+    let rows = layers.iter().map(Vec::len).max().unwrap_or(0) as u32;
+    let mut canvas = RgbaImage::new((cols * tile_w).max(1), (rows * tile_h).max(1));
+    for layer in layers {
+        for (row, cells) in layer.iter().enumerate() {
+            for (col, image) in cells.iter().enumerate() {
+                if let Some(image) = image {
+                    let x = col as u32 * tile_w;
+                    let y = (row as u32 + 1) * tile_h;
+                    draw(
+                        &mut canvas,
+                        image,
+                        i64::from(x),
+                        i64::from(y) - i64::from(image.height()),
+                        1.0,
+                    );
+                }
+            }
+        }
+    }
+    canvas
+}
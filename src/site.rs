@@ -0,0 +1,323 @@
+//! `site` preview style: a static catalog of the exported sprites in `previews/site/`.
+//!
+//! The page is one `index.html` with its styles, script and data inline, next to copies of
+//! the exports from `welder build` under `img/<res>x/`, so the folder works offline and can
+//! be uploaded as-is. Trimmed exports are placed back at their offset inside the original
+//! canvas using the build manifest, which keeps animation frames from jittering.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+use serde_json::{json, Value};
+
+use crate::{
+    animation_groups, export_key, normalize_for_glob, read_build_manifest, Config, Sprite,
+    BUILD_MANIFEST,
+};
+
+pub fn write(cfg: &Config, sprites: &[Sprite], dry_run: bool) -> Result<()> {
+    let site = cfg.paths.previews.join("site");
+    let manifest = read_build_manifest(&cfg.paths.dist.join(BUILD_MANIFEST))?;
+    let trims = manifest
+        .sources
+        .values()
+        .flat_map(|entry| &entry.sprites)
+        .collect::<BTreeMap<_, _>>();
+    // The build skips fully transparent sprites, so there is nothing to show for them.
+    let transparent = manifest
+        .sources
+        .values()
+        .flat_map(|entry| &entry.transparent)
+        .collect::<BTreeSet<_>>();
+
+    let mut resolutions = cfg.build.resolutions.clone();
+    resolutions.sort_unstable();
+    resolutions.dedup();
+
+    let mut copies = Vec::new();
+    let mut entries = Vec::with_capacity(sprites.len());
+    let mut missing = 0usize;
+    // Position of each sprite in `entries`, for animation frames.
+    let mut entry_of = vec![None; sprites.len()];
+    for (idx, sprite) in sprites.iter().enumerate() {
+        let name = normalize_for_glob(&sprite.path);
+        if transparent.contains(&name) {
+            continue;
+        }
+        entry_of[idx] = Some(entries.len());
+        let trim = trims.get(&name).and_then(|t| **t);
+        let (source_w, source_h) = match trim {
+            Some(t) => (t.source_w, t.source_h),
+            None => (sprite.image.width(), sprite.image.height()),
+        };
+        let mut images = serde_json::Map::new();
+        for &factor in &resolutions {
+            let key = export_key(None, factor, &name);
+            let from = cfg.paths.exports.join(&key);
+            let Ok((w, h)) = image::image_dimensions(&from) else {
+                missing += 1;
+                continue;
+            };
+            let (x, y) = trim.map_or((0, 0), |t| (t.x * factor, t.y * factor));
+            images.insert(
+                factor.to_string(),
+                json!({ "src": format!("img/{key}"), "x": x, "y": y, "w": w, "h": h }),
+            );
+            copies.push((from, site.join("img").join(&key)));
+        }
+        entries.push(json!({
+            "name": name,
+            "width": source_w,
+            "height": source_h,
+            "tags": sprite.meta.tags,
+            "images": images,
+        }));
+    }
+    if copies.is_empty() {
+        bail!(
+            "no exports found under {}; run `welder build` before the site style",
+            cfg.paths.exports.display()
+        );
+    }
+    if missing > 0 {
+        eprintln!(
+            "warning: preview: {missing} export(s) missing from {}; run `welder build` to refresh the site",
+            cfg.paths.exports.display()
+        );
+    }
+
+    let animations = animation_groups(cfg, sprites)?
+        .into_iter()
+        .filter_map(|anim| {
+            let frames = anim
+                .frames
+                .iter()
+                .filter_map(|&idx| entry_of[idx])
+                .collect::<Vec<_>>();
+            (!frames.is_empty()).then(|| {
+                json!({
+                    "name": anim.name,
+                    "frames": frames,
+                    "frame_ms": anim.frame_ms,
+                    "loop": anim.looped,
+                })
+            })
+        })
+        .collect::<Vec<_>>();
+    let data = json!({
+        "pack": {
+            "name": cfg.pack.name,
+            "version": cfg.pack.semver,
+            "author": cfg.pack.author,
+            "license": cfg.pack.license,
+        },
+        "resolutions": resolutions,
+        "sprites": entries,
+        "animations": animations,
+    });
+
+    let index = site.join("index.html");
+    if dry_run {
+        println!(
+            "[dry-run] copy {} export(s) to {}",
+            copies.len(),
+            site.display()
+        );
+        println!("[dry-run] write {}", index.display());
+        return Ok(());
+    }
+    // Rebuilt from scratch so removed sprites do not linger.
+    if site.exists() {
+        fs::remove_dir_all(&site).with_context(|| format!("failed removing {}", site.display()))?;
+    }
+    for (from, to) in &copies {
+        if let Some(parent) = to.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("failed creating {}", parent.display()))?;
+        }
+        fs::copy(from, to)
+            .with_context(|| format!("failed copying {} to {}", from.display(), to.display()))?;
+    }
+    write_index(&index, &cfg.pack.name, &data)
+}
+
+fn write_index(path: &Path, title: &str, data: &Value) -> Result<()> {
+    // `</` would end the inline script early.
+    let data = serde_json::to_string(data)
+        .context("failed encoding site data")?
+        .replace("</", "<\\/");
+    let html = INDEX_HTML
+        .replace("{{title}}", &escape_html(title))
+        .replace("{{data}}", &data);
+    fs::write(path, html).with_context(|| format!("failed writing {}", path.display()))
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+const INDEX_HTML: &str = r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>{{title}}</title>
+<style>
+  :root { color-scheme: dark; --bg: #141414; --card: #1f1f1f; --text: #e8e8e8; --dim: #9a9a9a; }
+  * { box-sizing: border-box; }
+  body { margin: 0; background: var(--bg); color: var(--text); font: 14px/1.4 system-ui, sans-serif; }
+  header { position: sticky; top: 0; z-index: 1; display: flex; flex-wrap: wrap; gap: 12px;
+           align-items: baseline; padding: 16px 24px; background: var(--bg); border-bottom: 1px solid #2a2a2a; }
+  h1 { margin: 0; font-size: 20px; }
+  h2 { margin: 24px 24px 8px; font-size: 16px; color: var(--dim); }
+  .meta { color: var(--dim); }
+  #search { margin-left: auto; min-width: 240px; padding: 6px 10px; border: 1px solid #333;
+            border-radius: 4px; background: #0e0e0e; color: var(--text); }
+  .grid { display: flex; flex-wrap: wrap; gap: 12px; padding: 0 24px 24px; }
+  .card { background: var(--card); border-radius: 6px; padding: 12px; }
+  .card[hidden] { display: none; }
+  .name { font-weight: 600; word-break: break-all; }
+  .tags { margin-top: 4px; }
+  .tag { display: inline-block; margin: 0 4px 4px 0; padding: 0 6px; border-radius: 3px;
+         background: #333; color: var(--dim); font-size: 12px; }
+  .sizes { display: flex; flex-wrap: wrap; gap: 12px; align-items: flex-end; margin-top: 8px; }
+  .size { color: var(--dim); font-size: 12px; text-align: center; }
+  .box { position: relative; margin-bottom: 4px;
+         background: repeating-conic-gradient(#2c2c2c 0 25%, #242424 0 50%) 0 0 / 16px 16px; }
+  .box img { position: absolute; image-rendering: pixelated; }
+  .anim .box { cursor: pointer; }
+  #empty { padding: 0 24px; color: var(--dim); }
+</style>
+</head>
+<body>
+<header>
+  <h1>{{title}}</h1>
+  <span class="meta" id="meta"></span>
+  <input id="search" type="search" placeholder="Search names and tags" autofocus>
+</header>
+<h2 id="anim-title">Animations</h2>
+<div class="grid" id="anims"></div>
+<h2>Sprites</h2>
+<div class="grid" id="sprites"></div>
+<p id="empty" hidden>No matches.</p>
+<script id="data" type="application/json">{{data}}</script>
+<script>
+"use strict";
+const data = JSON.parse(document.getElementById("data").textContent);
+const el = (tag, cls, text) => {
+  const node = document.createElement(tag);
+  if (cls) node.className = cls;
+  if (text !== undefined) node.textContent = text;
+  return node;
+};
+
+const pack = data.pack;
+document.getElementById("meta").textContent = [
+  pack.version && "v" + pack.version, pack.author, pack.license,
+  data.sprites.length + " sprites", data.animations.length + " animations",
+].filter(Boolean).join(" · ");
+
+// One checkerboard box per resolution, sized to the untrimmed sprite.
+function sizes(sprite, onBox) {
+  const row = el("div", "sizes");
+  for (const res of data.resolutions) {
+    const image = sprite.images[res];
+    if (!image) continue;
+    const cell = el("div", "size");
+    const box = el("div", "box");
+    box.style.width = sprite.width * res + "px";
+    box.style.height = sprite.height * res + "px";
+    const img = el("img");
+    img.src = image.src;
+    img.alt = sprite.name + " at " + res + "x";
+    img.style.left = image.x + "px";
+    img.style.top = image.y + "px";
+    img.width = image.w;
+    img.height = image.h;
+    box.append(img);
+    cell.append(box, res + "x · " + sprite.width * res + "×" + sprite.height * res);
+    row.append(cell);
+    if (onBox) onBox(res, img, box);
+  }
+  return row;
+}
+
+function tags(list) {
+  const row = el("div", "tags");
+  for (const tag of list) row.append(el("span", "tag", tag));
+  return row;
+}
+
+const cards = [];
+for (const sprite of data.sprites) {
+  const card = el("div", "card");
+  card.append(el("div", "name", sprite.name), tags(sprite.tags), sizes(sprite));
+  document.getElementById("sprites").append(card);
+  cards.push({ card, text: (sprite.name + " " + sprite.tags.join(" ")).toLowerCase() });
+}
+
+for (const anim of data.animations) {
+  const frames = anim.frames.map((i) => data.sprites[i]);
+  const card = el("div", "card anim");
+  card.title = anim.loop ? anim.name : "Click to replay";
+  const players = [];
+  const row = sizes(frames[0], (res, img) => players.push({ res, img }));
+  let frame = 0;
+  let timer = null;
+  const show = () => {
+    const sprite = frames[frame];
+    for (const { res, img } of players) {
+      const image = sprite.images[res];
+      if (!image) continue;
+      img.src = image.src;
+      img.style.left = image.x + "px";
+      img.style.top = image.y + "px";
+      img.width = image.w;
+      img.height = image.h;
+    }
+  };
+  const tick = () => {
+    if (frame + 1 < frames.length) frame += 1;
+    else if (anim.loop) frame = 0;
+    else { timer = null; return; }
+    show();
+    timer = setTimeout(tick, anim.frame_ms);
+  };
+  const play = () => {
+    clearTimeout(timer);
+    frame = 0;
+    show();
+    timer = setTimeout(tick, anim.frame_ms);
+  };
+  card.addEventListener("click", play);
+  const tagList = [...new Set(frames.flatMap((s) => s.tags))];
+  card.append(
+    el("div", "name", anim.name),
+    el("div", "meta", frames.length + " frames · " + anim.frame_ms + " ms" + (anim.loop ? " · loop" : "")),
+    tags(tagList),
+    row,
+  );
+  document.getElementById("anims").append(card);
+  cards.push({ card, text: (anim.name + " " + tagList.join(" ")).toLowerCase() });
+  play();
+}
+if (!data.animations.length) document.getElementById("anim-title").hidden = true;
+
+document.getElementById("search").addEventListener("input", (event) => {
+  const terms = event.target.value.toLowerCase().split(/\s+/).filter(Boolean);
+  let shown = 0;
+  for (const { card, text } of cards) {
+    card.hidden = !terms.every((term) => text.includes(term));
+    if (!card.hidden) shown += 1;
+  }
+  document.getElementById("empty").hidden = shown > 0;
+});
+</script>
+</body>
+</html>
+"#;
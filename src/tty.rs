@@ -0,0 +1,218 @@
+//! Terminal output for `welder preview --tty`: images drawn with the kitty graphics
+//! protocol, sixel, or ANSI truecolor half blocks, nearest-scaled to the terminal width.
+//!
+//! Without a pixel size from the terminal, graphics modes assume cells are at least
+//! `CELL_PX` pixels so images never overflow the width. `auto` picks kitty or sixel from
+//! well-known `TERM` / `TERM_PROGRAM` values and falls back to half blocks, which work in
+//! any truecolor terminal (and inside tmux, where graphics need passthrough).
+
+use std::fs::File;
+use std::io::{Cursor, Write};
+use std::process::{Command, Stdio};
+
+use anyhow::{bail, Context, Result};
+use base64::Engine as _;
+use image::imageops::{self, FilterType};
+use image::{ImageFormat, Rgba, RgbaImage};
+
+use crate::anim::Palette;
+
+/// Smallest cell size, in pixels, assumed for kitty and sixel output.
+const CELL_PX: (u32, u32) = (8, 16);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    Kitty,
+    Sixel,
+    Blocks,
+}
+
+impl Mode {
+    pub fn parse(value: &str) -> Result<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "auto" => Ok(detect()),
+            "kitty" => Ok(Mode::Kitty),
+            "sixel" => Ok(Mode::Sixel),
+            "blocks" => Ok(Mode::Blocks),
+            _ => bail!("--tty must be auto, kitty, sixel or blocks (got '{value}')"),
+        }
+    }
+}
+
+fn detect() -> Mode {
+    let var = |name| std::env::var(name).unwrap_or_default();
+    if std::env::var_os("TMUX").is_some() {
+        return Mode::Blocks;
+    }
+    let term = var("TERM");
+    let program = var("TERM_PROGRAM");
+    if term == "xterm-kitty"
+        || std::env::var_os("KITTY_WINDOW_ID").is_some()
+        || ["WezTerm", "ghostty"].contains(&program.as_str())
+    {
+        Mode::Kitty
+    } else if term.contains("sixel") || ["foot", "mlterm", "yaft-256color"].contains(&term.as_str())
+    {
+        Mode::Sixel
+    } else {
+        Mode::Blocks
+    }
+}
+
+/// Terminal size in cells: `COLUMNS` / `LINES`, then `stty size`, then 80x24.
+pub fn size() -> (u32, u32) {
+    let env = |name| std::env::var(name).ok().and_then(|v| v.trim().parse().ok());
+    let stty = || {
+        let tty = File::open("/dev/tty").ok()?;
+        let output = Command::new("stty")
+            .arg("size")
+            .stdin(tty)
+            .stderr(Stdio::null())
+            .output()
+            .ok()?;
+        let text = String::from_utf8(output.stdout).ok()?;
+        let (rows, cols) = text.trim().split_once(' ')?;
+        Some((cols.parse().ok()?, rows.parse().ok()?))
+    };
+    let (stty_cols, stty_rows) = stty().unwrap_or((80, 24));
+    let cols: u32 = env("COLUMNS").unwrap_or(stty_cols);
+    let rows: u32 = env("LINES").unwrap_or(stty_rows);
+    (cols.max(1), rows.max(1))
+}
+
+/// Writes `image` to `out` in `mode`, fitted to a `cols` x `rows` terminal.
+pub fn show(
+    out: &mut impl Write,
+    image: &RgbaImage,
+    mode: Mode,
+    (cols, rows): (u32, u32),
+) -> Result<()> {
+    match mode {
+        Mode::Kitty => kitty(out, &fit(image, cols * CELL_PX.0, rows * CELL_PX.1)),
+        Mode::Sixel => sixel(out, &fit(image, cols * CELL_PX.0, rows * CELL_PX.1)),
+        // Each character cell shows two pixels stacked vertically.
+        Mode::Blocks => blocks(out, &fit(image, cols, rows.saturating_sub(1) * 2)),
+    }
+    .context("failed writing to the terminal")
+}
+
+/// Nearest-scales `image` to `max_w` wide when it is wider; smaller images are enlarged by
+/// the largest whole factor that keeps them within `max_w` x `max_h`.
+fn fit(image: &RgbaImage, max_w: u32, max_h: u32) -> RgbaImage {
+    let (w, h) = image.dimensions();
+    if w > max_w {
+        let new_h = (u64::from(h) * u64::from(max_w) / u64::from(w)).max(1) as u32;
+        return imageops::resize(image, max_w, new_h, FilterType::Nearest);
+    }
+    let factor = (max_w / w.max(1)).min(max_h / h.max(1)).max(1);
+    if factor == 1 {
+        return image.clone();
+    }
+    imageops::resize(image, w * factor, h * factor, FilterType::Nearest)
+}
+
+fn kitty(out: &mut impl Write, image: &RgbaImage) -> Result<()> {
+    let mut png = Vec::new();
+    image
+        .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+        .context("failed encoding PNG")?;
+    let data = base64::engine::general_purpose::STANDARD.encode(png);
+    let chunks = data.as_bytes().chunks(4096).collect::<Vec<_>>();
+    for (idx, chunk) in chunks.iter().enumerate() {
+        let more = u8::from(idx + 1 < chunks.len());
+        let keys = if idx == 0 {
+            format!("a=T,f=100,q=2,m={more}")
+        } else {
+            format!("m={more}")
+        };
+        write!(out, "\x1b_G{keys};")?;
+        out.write_all(chunk)?;
+        write!(out, "\x1b\\")?;
+    }
+    writeln!(out)?;
+    Ok(())
+}
+
+fn sixel(out: &mut impl Write, image: &RgbaImage) -> Result<()> {
+    let (width, height) = image.dimensions();
+    let palette = Palette::build(std::iter::once(image), 256);
+    // P2 = 1: pixels left unset stay transparent.
+    write!(out, "\x1bP0;1;0q\"1;1;{width};{height}")?;
+    for (idx, [r, g, b]) in palette.colors.iter().enumerate() {
+        let pct = |c: u8| (u32::from(c) * 100 + 127) / 255;
+        write!(out, "#{idx};2;{};{};{}", pct(*r), pct(*g), pct(*b))?;
+    }
+
+    let indices = image.pixels().map(|p| palette.index(p)).collect::<Vec<_>>();
+    let mut band = vec![vec![0u8; width as usize]; palette.colors.len()];
+    for top in (0..height).step_by(6) {
+        for row in band.iter_mut() {
+            row.fill(0);
+        }
+        let mut used = vec![false; palette.colors.len()];
+        for dy in 0..6.min(height - top) {
+            let y = top + dy;
+            for x in 0..width {
+                if let Some(idx) = indices[(y * width + x) as usize] {
+                    band[idx][x as usize] |= 1 << dy;
+                    used[idx] = true;
+                }
+            }
+        }
+        for (idx, row) in band.iter().enumerate().filter(|(idx, _)| used[*idx]) {
+            write!(out, "#{idx}")?;
+            let mut x = 0;
+            while x < row.len() {
+                let bits = row[x];
+                let run = row[x..].iter().take_while(|&&b| b == bits).count();
+                let ch = char::from(63 + bits);
+                if run > 3 {
+                    write!(out, "!{run}{ch}")?;
+                } else {
+                    for _ in 0..run {
+                        write!(out, "{ch}")?;
+                    }
+                }
+                x += run;
+            }
+            write!(out, "$")?;
+        }
+        write!(out, "-")?;
+    }
+    writeln!(out, "\x1b\\")?;
+    Ok(())
+}
+
+/// Upper half blocks: the foreground colors the top pixel and the background the bottom
+/// one. Transparent pixels keep the terminal's own background.
+fn blocks(out: &mut impl Write, image: &RgbaImage) -> Result<()> {
+    let (width, height) = image.dimensions();
+    let visible = |x: u32, y: u32| {
+        (y < height)
+            .then(|| *image.get_pixel(x, y))
+            .filter(|p: &Rgba<u8>| p[3] >= crate::anim::ALPHA_CUTOFF)
+    };
+    let mut line = String::new();
+    for y in (0..height).step_by(2) {
+        line.clear();
+        for x in 0..width {
+            match (visible(x, y), visible(x, y + 1)) {
+                (Some(top), Some(bottom)) => line.push_str(&format!(
+                    "\x1b[38;2;{};{};{};48;2;{};{};{}m\u{2580}",
+                    top[0], top[1], top[2], bottom[0], bottom[1], bottom[2]
+                )),
+                (Some(top), None) => line.push_str(&format!(
+                    "\x1b[49;38;2;{};{};{}m\u{2580}",
+                    top[0], top[1], top[2]
+                )),
+                (None, Some(bottom)) => line.push_str(&format!(
+                    "\x1b[49;38;2;{};{};{}m\u{2584}",
+                    bottom[0], bottom[1], bottom[2]
+                )),
+                (None, None) => line.push_str("\x1b[0m "),
+            }
+        }
+        writeln!(out, "{line}\x1b[0m")?;
+    }
+    Ok(())
+}